@@ -0,0 +1,137 @@
+// -- attack.rs --
+
+use crate::{
+    engine::Engine,
+    scoring::{Clear, Spin},
+};
+
+// --
+
+// the lines of garbage a clear sends, as in the guideline games: a line less than cleared,
+// all four for a tetris, twice as many for a t-spin, one more back to back, more and more
+// for a combo and ten for a perfect clear.
+pub(crate) fn lines_sent(clear: &Clear) -> i32 {
+    // by the clears in a row before this one.
+    const COMBO: [i32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+    const PERFECT: i32 = 10;
+
+    if clear.lines == 0 {
+        return 0;
+    }
+    let mut lines = match (clear.spin, clear.lines) {
+        (Spin::Full, n) => n * 2,
+        (Spin::Mini, n) => n - 1,
+        (Spin::None, 4) => 4,
+        (Spin::None, n) => n - 1,
+    };
+    if clear.b2b {
+        lines += 1;
+    }
+    lines += COMBO[(clear.combo.max(0) as usize).min(COMBO.len() - 1)];
+    if clear.perfect {
+        lines += PERFECT;
+    }
+    lines
+}
+
+// --
+
+// the garbage sent to a board which has not come up yet. the attacks of the board cancel
+// it first, only what is left of them is sent on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Incoming {
+    lines: i32,
+}
+
+impl Incoming {
+    // how often the hole of the garbage moves.
+    const GARBAGE_CHANGE: u32 = 30;
+
+    pub(crate) fn lines(&self) -> i32 {
+        self.lines
+    }
+    // never more than would fill the board.
+    pub(crate) fn receive(&mut self, lines: i32) {
+        self.lines = (self.lines + lines.max(0)).min(Engine::ROWS_COUNT);
+    }
+    // the lines of the attack left to send.
+    pub(crate) fn cancel(&mut self, attack: i32) -> i32 {
+        let cancelled = attack.min(self.lines);
+        self.lines -= cancelled;
+        attack - cancelled
+    }
+    // all of it comes up.
+    pub(crate) fn take(&mut self) -> i32 {
+        std::mem::take(&mut self.lines)
+    }
+    // a block locked on the board: a clear attacks with what is left after cancelling,
+    // otherwise the garbage comes up under the stack. the lines to send, or None when the
+    // garbage pushed the stack over the top.
+    pub(crate) fn locked(&mut self, engine: &mut Engine) -> Option<i32> {
+        match engine.last_clear() {
+            Some(clear) if clear.lines > 0 => Some(self.cancel(lines_sent(clear))),
+            _ if self.lines > 0 => {
+                let holes = engine.garbage_holes(self.take(), Self::GARBAGE_CHANGE);
+                engine.insert_garbage(&holes).then_some(0)
+            }
+            _ => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::Status, input::Action};
+
+    fn clear(lines: i32, spin: Spin) -> Clear {
+        Clear {
+            lines,
+            spin,
+            b2b: false,
+            combo: 0,
+            perfect: false,
+        }
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(lines_sent(&clear(1, Spin::None)), 0);
+        assert_eq!(lines_sent(&clear(3, Spin::None)), 2);
+        assert_eq!(lines_sent(&clear(4, Spin::None)), 4);
+        assert_eq!(lines_sent(&clear(2, Spin::Full)), 4);
+        assert_eq!(lines_sent(&clear(2, Spin::Mini)), 1);
+        let b2b = Clear {
+            b2b: true,
+            combo: 3,
+            ..clear(4, Spin::None)
+        };
+        assert_eq!(lines_sent(&b2b), 4 + 1 + 1);
+        let perfect = Clear {
+            perfect: true,
+            ..clear(2, Spin::None)
+        };
+        assert_eq!(lines_sent(&perfect), 11);
+    }
+
+    #[test]
+    fn cancelling() {
+        let mut incoming = Incoming::default();
+        incoming.receive(3);
+        assert_eq!(incoming.cancel(2), 0);
+        assert_eq!(incoming.lines(), 1);
+        assert_eq!(incoming.cancel(4), 3);
+        assert_eq!(incoming.take(), 0);
+    }
+
+    #[test]
+    fn garbage_comes_up() {
+        let mut engine = Engine::new(1, Default::default());
+        let mut incoming = Incoming::default();
+        incoming.receive(2);
+        while !matches!(engine.step(&[Action::HardDrop]), Some(Status::Freeze)) {}
+        assert_eq!(incoming.locked(&mut engine), Some(0));
+        assert_eq!(engine.garbage_rows(), 2);
+        assert_eq!(incoming.lines(), 0);
+    }
+}
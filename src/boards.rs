@@ -7,18 +7,11 @@ use fltk::{
     prelude::{WidgetBase, WidgetExt},
     widget::*,
 };
-use std::ops::{Deref, DerefMut};
-
-// --
-
-pub(crate) trait UnsafeFrom {
-    fn unsafe_from<T>(p: *const T) -> &'static T {
-        unsafe { &*p }
-    }
-    fn unsafe_mut_from<T>(p: *mut T) -> &'static mut T {
-        unsafe { &mut *p }
-    }
-}
+use std::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
 
 // --
 
@@ -36,7 +29,7 @@ impl TextBoard {
                 Color::FrameDefault,
             );
             draw::set_draw_color(Color::ForeGround);
-            draw::set_font(draw::font(), 16);
+            draw::set_font(draw::font(), 14);
             draw::draw_text2(
                 &text,
                 w.x(),
@@ -52,50 +45,48 @@ impl TextBoard {
 
 // --
 
+// the value is shared with the draw callback of the widget.
 pub(crate) struct ValueBoard {
     wid: Widget,
-    value: i32,
+    value: Rc<RefCell<String>>,
 }
 impl ValueBoard {
-    pub(crate) fn new_box(x: i32, y: i32, w: i32, h: i32, label: &'static str) -> Box<Self> {
+    const FONT_SIZE: i32 = 36;
+
+    pub(crate) fn new(x: i32, y: i32, w: i32, h: i32, label: &'static str) -> Self {
         let mut wid = Widget::new(x, y, w, h, label).with_align(Align::Top | Align::Left);
         wid.set_label_size(24);
 
-        let mut ptr = Box::new(Self { wid, value: 0 });
-        let vb = ptr.as_mut() as *mut Self;
-        ptr.wid.draw(move |_| {
-            let vb = Self::unsafe_mut_from(vb);
-            vb.draw();
-        });
-        ptr
+        let value = Rc::new(RefCell::new(0.to_string()));
+        let v = value.clone();
+        wid.draw(move |w| Self::draw(w, &v.borrow()));
+        Self { wid, value }
     }
-    fn draw(&mut self) {
+    fn draw(w: &Widget, value: &str) {
         draw::draw_box(
             FrameType::FlatBox,
-            self.wid.x(),
-            self.wid.y(),
-            self.wid.width(),
-            self.wid.height(),
+            w.x(),
+            w.y(),
+            w.width(),
+            w.height(),
             Color::FrameDefault,
         );
         draw::set_draw_color(Color::ForeGround);
-        draw::set_font(draw::font(), 36);
-        let str = format!("{}", self.value);
-        draw::draw_text2(
-            &str,
-            self.wid.x(),
-            self.wid.y(),
-            self.wid.width(),
-            self.wid.height(),
-            Align::Center,
-        );
+        // smaller for the long ones, e.g. a time to the millisecond.
+        let mut size = Self::FONT_SIZE;
+        draw::set_font(draw::font(), size);
+        while size > 12 && draw::width(value) as i32 > w.width() {
+            size -= 2;
+            draw::set_font(draw::font(), size);
+        }
+        draw::draw_text2(value, w.x(), w.y(), w.width(), w.height(), Align::Center);
     }
     pub(crate) fn set_value(&mut self, v: i32) {
-        self.value = v;
-        self.wid.redraw();
+        self.set_text(v.to_string());
     }
-    pub(crate) fn value(&self) -> i32 {
-        self.value
+    pub(crate) fn set_text(&mut self, text: String) {
+        *self.value.borrow_mut() = text;
+        self.wid.redraw();
     }
 }
 impl Deref for ValueBoard {
@@ -110,49 +101,119 @@ impl DerefMut for ValueBoard {
         &mut self.wid
     }
 }
-impl UnsafeFrom for ValueBoard {}
 
 // --
 
+#[derive(Default)]
+struct Strip {
+    blocks: Vec<Block>,
+    greyed: bool,
+    hidden: bool,
+}
+
 pub(crate) struct BlockBoard {
     wid: Widget,
-    block: Option<Block>,
+    strip: Rc<RefCell<Strip>>,
 }
 
 impl BlockBoard {
-    pub(crate) fn new_box(x: i32, y: i32, w: i32, h: i32, label: &'static str) -> Box<Self> {
+    const COLORS: [Color; Block::BLOCKS_COUNT] = [
+        Color::Red,         // I
+        Color::DarkBlue,    // O
+        Color::DarkCyan,    // Z
+        Color::DarkMagenta, // S
+        Color::Blue,        // L
+        Color::DarkGreen,   // J
+        Color::DarkRed,     // T
+    ];
+    pub(crate) const CELL_INNER: i32 = 28;
+    pub(crate) const CELL_EDGE: i32 = 1;
+
+    pub(crate) const fn cell_size() -> i32 {
+        Self::CELL_EDGE + Self::CELL_INNER + Self::CELL_EDGE
+    }
+    // a block at full size.
+    pub(crate) const fn block_size() -> i32 {
+        Self::cell_size() * Block::CELLS_COUNT
+    }
+    // the cells of a block in its own color, or in c. smaller (or bigger) cells keep the
+    // same edge around them.
+    pub(crate) fn draw_block(b: &Block, x: i32, y: i32, cell_size: i32, c: Option<Color>) {
+        let inner = cell_size - Self::CELL_EDGE * 2;
+        let mut cell_x = x + cell_size * 3 + Self::CELL_EDGE;
+        let mut cell_y = y + cell_size * 3 + Self::CELL_EDGE;
+        let mut data = b.data();
+
+        draw::set_draw_color(c.unwrap_or(Self::COLORS[b.index()]));
+        for _ in 0..Block::CELLS_COUNT {
+            for _ in 0..Block::CELLS_COUNT {
+                if data & 1 == 1 {
+                    draw::draw_rectf(cell_x, cell_y, inner, inner);
+                }
+                data >>= 1;
+                cell_x -= cell_size;
+            }
+            cell_x = x + cell_size * 3 + Self::CELL_EDGE;
+            cell_y -= cell_size;
+        }
+    }
+    pub(crate) fn new(x: i32, y: i32, w: i32, h: i32, label: &'static str) -> Self {
         let mut wid = Widget::new(x, y, w, h, label);
         wid.set_label_size(24);
         wid.set_align(Align::Top | Align::Left);
-        let block = Some(Block::new());
-        let mut ptr = Box::new(Self { wid, block });
 
-        let bb = ptr.as_mut() as *mut Self;
-        ptr.wid.draw(move |_| {
-            let bb = Self::unsafe_mut_from(bb);
-            bb.draw();
-        });
-        ptr.wid.redraw();
+        let strip = Rc::new(RefCell::new(Strip::default()));
+        let s = strip.clone();
+        wid.draw(move |w| Self::draw(w, &s.borrow()));
+        wid.redraw();
 
-        ptr
+        Self { wid, strip }
     }
-    fn draw(&mut self) {
-        let x = self.x() + (self.width() - Block::size()) / 4;
-        let y = self.y() + (self.height() - Block::size()) / 2;
+    // the height of a strip with one block at full size and the others at half size.
+    pub(crate) const fn strip_height(count: i32) -> i32 {
+        Self::block_size() + (count - 1) * Self::block_size() / 2
+    }
+    fn draw(w: &Widget, strip: &Strip) {
         draw::draw_box(
             FrameType::FlatBox,
-            self.x(),
-            self.y(),
-            self.width(),
-            self.height(),
+            w.x(),
+            w.y(),
+            w.width(),
+            w.height(),
             Color::FrameDefault,
         );
-        self.block.as_ref().and_then(|b| Some(b.draw(x, y, None)));
+        if strip.hidden {
+            return;
+        }
+        let color = strip.greyed.then_some(Color::Dark3);
+        let mut y = w.y();
+        for (i, b) in strip.blocks.iter().enumerate() {
+            let cell_size = if i == 0 {
+                Self::cell_size()
+            } else {
+                Self::cell_size() / 2
+            };
+            let size = cell_size * Block::CELLS_COUNT;
+            Self::draw_block(b, w.x() + (w.width() - size) / 4, y, cell_size, color);
+            y += size;
+        }
+    }
+    pub(crate) fn set_block(&mut self, block: Option<Block>) {
+        self.set_blocks(block.into_iter().collect());
+    }
+    pub(crate) fn set_blocks(&mut self, blocks: Vec<Block>) {
+        self.strip.borrow_mut().blocks = blocks;
+        self.wid.redraw();
+    }
+    // the block is drawn in grey, e.g. when it can not be used for now.
+    pub(crate) fn set_greyed(&mut self, greyed: bool) {
+        self.strip.borrow_mut().greyed = greyed;
+        self.wid.redraw();
     }
-    pub(crate) fn next(&mut self) -> Block {
-        let block = self.block.replace(Block::new()).unwrap();
+    // no block is drawn at all, e.g. while paused so that nobody plans ahead.
+    pub(crate) fn set_hidden(&mut self, hidden: bool) {
+        self.strip.borrow_mut().hidden = hidden;
         self.wid.redraw();
-        block
     }
 }
 impl Deref for BlockBoard {
@@ -167,4 +228,3 @@ impl DerefMut for BlockBoard {
         &mut self.wid
     }
 }
-impl UnsafeFrom for BlockBoard {}
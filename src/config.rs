@@ -0,0 +1,19 @@
+// -- config.rs --
+
+use std::{env, path::PathBuf};
+
+// --
+
+// where the files of the game are kept, e.g. ~/.config/tetris
+pub(crate) fn dir() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("tetris")
+}
+
+pub(crate) fn file(name: &str) -> PathBuf {
+    dir().join(name)
+}
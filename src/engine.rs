@@ -0,0 +1,1083 @@
+// -- engine.rs --
+
+use crate::{
+    blocks::{Block, Posture},
+    codec::{Reader, Writer},
+    gravity::GravityCurve,
+    input::Action,
+    queue::Queue,
+    randomizer::Randomizer,
+    rng::GameRng,
+    rotation::RotationSystem,
+    ruleset::{LockDelay, Ruleset},
+    scoring::{Clear, Scoring, Spin},
+};
+use rand::Rng;
+
+// --
+
+/*
+
+       first column is 0
+           |           last colume is 15
+           |                 |
+           V                 v
+    40  0b_1110_0000_0000_0111      <-- row number: 40, the top of the hidden rows
+    ..
+    21  0b_1110_0000_0000_0111      <-- row number: 21, the lowest hidden one
+    20  0b_1110_0000_0000_0111      <-- row number: 20, the top of the field
+    19  0b_1110_0000_0000_0111
+    18  0b_1110_0000_0000_0111
+    17  0b_1110_0000_0000_0111
+    16  0b_1110_0000_0000_0111
+    15  0b_1110_0000_0000_0111
+    14  0b_1110_0000_0000_0111
+    13  0b_1110_0000_0000_0111
+    12  0b_1110_0000_0000_0111
+    11  0b_1110_0000_0000_0111          dropping block
+    10  0b_1110_0010_0000_0111      <-- row: 10
+    9   0b_1110_0011_0000_0111          col: 6
+    8   0b_1110_0001_0000_0111
+    7   0b_1110_0000_0000_0111
+    6   0b_1110_0000_0000_0111
+    5   0b_1110_0000_0000_0111
+    4   0b_1110_0000_0000_0111
+    3   0b_1110_0000_0000_0111
+    2   0b_1110_0000_0000_0111
+    1   0b_1110_0000_0000_0111
+    0   0b_1111_1111_1111_1111      < --- row number: 0
+
+*/
+
+// a block put in its place, with how it came in, to tell how many keys it should have taken.
+#[derive(Clone, Copy)]
+pub(crate) struct Lock {
+    pub(crate) spawned: Block,
+    pub(crate) spawn_col: i32,
+    pub(crate) block: Block,
+    pub(crate) col: i32,
+}
+
+// how a game is lost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TopOut {
+    // the new block has no room.
+    Block,
+    // a block locked with all of its cells over the field.
+    Lock,
+    // garbage pushed the stack, or the block, over the hidden rows.
+    Garbage,
+}
+
+impl TopOut {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Block => "Block out",
+            Self::Lock => "Lock out",
+            Self::Garbage => "Garbage out",
+        }
+    }
+}
+
+pub(crate) enum Status {
+    NeedBlock,
+    Dropping,
+    Freeze,
+}
+
+// the rules of the game without any ui, the widgets only read from it.
+pub(crate) struct Engine {
+    rows: [u16; Engine::ROWS_COUNT as usize],
+    dropping: Option<Block>,
+    // the dropping block as it came in.
+    spawned: Option<Block>,
+    col: i32,
+    row: i32,
+    shadow_row: i32,
+    lowest_row: i32,
+    lock_frames: u32,
+    lock_resets: u32,
+    // rows fallen in part by gravity, one more is fallen when it gets to 1.
+    gravity: f64,
+    // the kick used by the last rotation, if nothing moved the block since then.
+    last_kick: Option<usize>,
+    queue: Queue,
+    hold: Option<Block>,
+    hold_locked: bool,
+    seed: u64,
+    rng: GameRng,
+    ruleset: Ruleset,
+    randomizer: Box<dyn Randomizer>,
+    rotation: Box<dyn RotationSystem>,
+
+    lines: i32,
+    level: i32,
+    score: i32,
+    scoring: Scoring,
+    last_clear: Option<Clear>,
+    // the blocks locked so far.
+    pieces: u32,
+    last_lock: Option<Lock>,
+    // the garbage rows left at the bottom of the stack, they are cleared from the top.
+    garbage_rows: i32,
+    // the holes of the garbage have numbers of their own, so that the blocks of a seed stay
+    // the same whatever garbage comes.
+    garbage_rng: GameRng,
+    last_hole: Option<i32>,
+    top_out: Option<TopOut>,
+}
+
+impl Engine {
+    pub(crate) const COLS_COUNT: i32 = 16;
+    // the ground, the field and as many hidden rows over it, where blocks may still go.
+    pub(crate) const VISIBLE_ROWS: i32 = 20;
+    pub(crate) const ROWS_COUNT: i32 = 1 + Self::VISIBLE_ROWS * 2;
+    // the top row of the box of a new block.
+    const SPAWN_ROW: i32 = Self::VISIBLE_ROWS;
+    pub(crate) const LEFT_EDGE_COL: i32 = 3;
+    pub(crate) const FIELD_COLS: i32 = 10;
+    pub(crate) const DEFAULT_ROW_DATA: u16 = 0b1110_0000_0000_0111;
+    const GROUND_ROW_DATA: u16 = 0b1111_1111_1111_1111;
+    const LINES_PER_LEVEL: i32 = 10;
+    const GARBAGE_SEED: u64 = 0x6761_7262_6167_6521;
+
+    pub(crate) fn new(seed: u64, ruleset: Ruleset) -> Self {
+        let mut rows = [Self::DEFAULT_ROW_DATA; Self::ROWS_COUNT as usize];
+        rows[0] = Self::GROUND_ROW_DATA;
+        let randomizer = ruleset.randomizer.create();
+        let rotation = ruleset.rotation.create();
+        let mut engine = Self {
+            rows,
+            dropping: None,
+            spawned: None,
+            col: rotation.spawn_col(),
+            row: Self::SPAWN_ROW,
+            shadow_row: 0,
+            lowest_row: Self::SPAWN_ROW,
+            lock_frames: 0,
+            lock_resets: 0,
+            gravity: 0.0,
+            last_kick: None,
+            queue: Queue::default(),
+            hold: None,
+            hold_locked: false,
+            seed,
+            rng: GameRng::new(seed),
+            ruleset,
+            randomizer,
+            rotation,
+            lines: 0,
+            level: 1,
+            score: 0,
+            scoring: Scoring::default(),
+            last_clear: None,
+            pieces: 0,
+            last_lock: None,
+            garbage_rows: 0,
+            garbage_rng: GameRng::new(seed ^ Self::GARBAGE_SEED),
+            last_hole: None,
+            top_out: None,
+        };
+        engine.refill();
+        engine
+    }
+    pub(crate) fn rows(&self) -> &[u16] {
+        &self.rows
+    }
+    pub(crate) fn dropping(&self) -> Option<&Block> {
+        self.dropping.as_ref()
+    }
+    pub(crate) fn col(&self) -> i32 {
+        self.col
+    }
+    pub(crate) fn row(&self) -> i32 {
+        self.row
+    }
+    pub(crate) fn shadow_row(&self) -> i32 {
+        self.shadow_row
+    }
+    pub(crate) fn next(&self) -> &Block {
+        self.queue.front().unwrap()
+    }
+    pub(crate) fn queue(&self) -> &Queue {
+        &self.queue
+    }
+    pub(crate) fn hold(&self) -> Option<&Block> {
+        self.hold.as_ref()
+    }
+    // hold is allowed once for every block.
+    pub(crate) fn hold_locked(&self) -> bool {
+        self.hold_locked
+    }
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub(crate) fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+    pub(crate) fn lines(&self) -> i32 {
+        self.lines
+    }
+    pub(crate) fn level(&self) -> i32 {
+        self.level
+    }
+    pub(crate) fn score(&self) -> i32 {
+        self.score
+    }
+    // what the last locked block cleared, if it is worth telling.
+    pub(crate) fn last_clear(&self) -> Option<&Clear> {
+        self.last_clear.as_ref()
+    }
+
+    pub(crate) fn pieces(&self) -> u32 {
+        self.pieces
+    }
+    pub(crate) fn last_lock(&self) -> Option<&Lock> {
+        self.last_lock.as_ref()
+    }
+    pub(crate) fn garbage_rows(&self) -> i32 {
+        self.garbage_rows
+    }
+    // why the game is over, if it is.
+    pub(crate) fn top_out(&self) -> Option<TopOut> {
+        self.top_out
+    }
+
+    // take the next block as the dropping one, return false if there is no room for it.
+    pub(crate) fn spawn(&mut self) -> bool {
+        let b = self.queue.pop().unwrap();
+        self.refill();
+        let placed = self.place(b);
+        if !placed {
+            self.top_out = Some(TopOut::Block);
+        }
+        placed
+    }
+    // one frame of the game, 1/60 of a second: gravity, then the lock delay.
+    pub(crate) fn frame(&mut self) -> Status {
+        if self.dropping.is_none() {
+            return Status::NeedBlock;
+        }
+
+        self.gravity += self.ruleset.gravity.g(self.level);
+        while self.gravity >= 1.0 {
+            self.gravity -= 1.0;
+            if !self.fall() {
+                self.gravity = 0.0;
+                if self.ruleset.lock_delay == LockDelay::None {
+                    self.lock();
+                    return Status::Freeze;
+                }
+            }
+        }
+
+        let (ms, resets) = match self.ruleset.lock_delay {
+            LockDelay::None => return Status::Dropping,
+            LockDelay::Limited { ms, resets } => (ms, resets),
+            LockDelay::Infinity { ms } => (ms, u32::MAX),
+        };
+        if !self.on_ground() {
+            self.lock_frames = 0;
+        } else {
+            self.lock_frames += 1;
+            let delay = ms * GravityCurve::FRAMES_PER_SECOND / 1000;
+            if self.lock_frames >= delay || self.lock_resets >= resets {
+                self.lock();
+                return Status::Freeze;
+            }
+        }
+        Status::Dropping
+    }
+    // a whole frame of play: a new block if there is none, the actions of the player, then
+    // gravity and the lock delay. None when the game is over, top_out() tells why.
+    pub(crate) fn step(&mut self, actions: &[Action]) -> Option<Status> {
+        if self.top_out.is_some() || (self.dropping.is_none() && !self.spawn()) {
+            return None;
+        }
+        let mut status = Status::Dropping;
+        for action in actions {
+            if let Status::Freeze = self.apply(*action) {
+                status = Status::Freeze;
+            }
+        }
+        if let Status::Freeze = self.frame() {
+            status = Status::Freeze;
+        }
+        if self.top_out.is_some() {
+            return None;
+        }
+        Some(status)
+    }
+    pub(crate) fn apply(&mut self, action: Action) -> Status {
+        if self.dropping.is_none() {
+            return Status::NeedBlock;
+        }
+        match action {
+            Action::MoveLeft => {
+                self.move_left();
+            }
+            Action::MoveRight => {
+                self.move_right();
+            }
+            Action::RotateCCW => {
+                self.rotate_anticlockwise();
+            }
+            Action::RotateCW => {
+                self.rotate_clockwise();
+            }
+            Action::SoftDrop => {
+                self.soft_drop();
+            }
+            Action::Hold => {
+                self.hold_block();
+            }
+            Action::HardDrop => return self.hard_drop(),
+            Action::Pause | Action::Save => {}
+        }
+        Status::Dropping
+    }
+    // start over with another seed, every block of the game comes from it.
+    pub(crate) fn reset(&mut self, seed: u64) {
+        self.dropping.take();
+        self.spawned.take();
+        self.rows
+            .iter_mut()
+            .skip(1)
+            .for_each(|r| *r = Self::DEFAULT_ROW_DATA);
+        self.seed = seed;
+        self.rng = GameRng::new(seed);
+        self.randomizer = self.ruleset.randomizer.create();
+        self.rotation = self.ruleset.rotation.create();
+        self.queue.clear();
+        self.refill();
+        self.hold = None;
+        self.hold_locked = false;
+        self.lines = 0;
+        self.level = 1;
+        self.score = 0;
+        self.scoring = Scoring::default();
+        self.last_clear = None;
+        self.pieces = 0;
+        self.last_lock = None;
+        self.garbage_rows = 0;
+        self.garbage_rng = GameRng::new(seed ^ Self::GARBAGE_SEED);
+        self.last_hole = None;
+        self.top_out = None;
+    }
+
+    // the hole columns of garbage rows, from the first pushed in to the last. every hole is
+    // under the one before, unless it moves with a chance of `change` percent.
+    pub(crate) fn garbage_holes(&mut self, count: i32, change: u32) -> Vec<i32> {
+        let mut holes = Vec::new();
+        for _ in 0..count {
+            let hole = match self.last_hole {
+                Some(h) if self.garbage_rng.gen_range(0..100) >= change => h,
+                // any other column.
+                Some(h) => {
+                    let c =
+                        Self::LEFT_EDGE_COL + self.garbage_rng.gen_range(0..Self::FIELD_COLS - 1);
+                    if c >= h {
+                        c + 1
+                    } else {
+                        c
+                    }
+                }
+                None => Self::LEFT_EDGE_COL + self.garbage_rng.gen_range(0..Self::FIELD_COLS),
+            };
+            self.last_hole = Some(hole);
+            holes.push(hole);
+        }
+        holes
+    }
+    // push garbage rows in from the bottom, right above the ground, the last one lowest.
+    // the dropping block goes up too if the stack comes into it. false if the stack or the
+    // block is pushed over the top of the hidden rows, the game is over.
+    pub(crate) fn insert_garbage(&mut self, holes: &[i32]) -> bool {
+        let top = Self::ROWS_COUNT as usize;
+        let n = holes.len().min(top - 1);
+        let mut spilled = self.rows[top - n..]
+            .iter()
+            .any(|r| *r != Self::DEFAULT_ROW_DATA);
+        self.rows.copy_within(1..top - n, 1 + n);
+        for (i, hole) in holes.iter().take(n).enumerate() {
+            self.rows[n - i] = Self::GROUND_ROW_DATA & !(0x8000 >> hole);
+        }
+        self.garbage_rows = (self.garbage_rows + n as i32).min(Self::ROWS_COUNT - 1);
+
+        if let Some(b) = self.dropping {
+            let row = self.row;
+            while self.collide_at(self.col, self.row, b.data()) {
+                self.row += 1;
+            }
+            self.lowest_row += self.row - row;
+            // the empty rows at the top of its box may go past the top, not its cells.
+            let empty = b.data().leading_zeros() as i32 / Block::CELLS_COUNT;
+            spilled |= self.row - empty >= Self::ROWS_COUNT;
+        }
+        self.shadow();
+        if spilled {
+            self.top_out = Some(TopOut::Garbage);
+        }
+        !spilled
+    }
+
+    // everything but the last clear and lock, which are only told once.
+    pub(crate) fn encode(&self, w: &mut Writer) {
+        self.ruleset.encode(w);
+        w.u64(self.seed);
+        w.u64(self.rng.state());
+        self.randomizer.encode(w);
+        self.rows.iter().for_each(|r| w.u16(*r));
+        Self::encode_block(w, self.dropping.as_ref());
+        Self::encode_block(w, self.spawned.as_ref());
+        w.i32(self.col);
+        w.i32(self.row);
+        w.i32(self.lowest_row);
+        w.u32(self.lock_frames);
+        w.u32(self.lock_resets);
+        w.f64(self.gravity);
+        w.u8(self.last_kick.map_or(u8::MAX, |k| k as u8));
+        w.varint(self.queue.iter().count() as u64);
+        self.queue
+            .iter()
+            .for_each(|b| Self::encode_block(w, Some(b)));
+        Self::encode_block(w, self.hold.as_ref());
+        w.bool(self.hold_locked);
+        w.i32(self.lines);
+        w.i32(self.level);
+        w.i32(self.score);
+        self.scoring.encode(w);
+        w.u32(self.pieces);
+        w.u32(self.garbage_rows as u32);
+        w.u64(self.garbage_rng.state());
+        w.u8(self.last_hole.map_or(u8::MAX, |h| h as u8));
+    }
+    pub(crate) fn decode(r: &mut Reader) -> Result<Self, String> {
+        let ruleset = Ruleset::decode(r)?;
+        let mut e = Self::new(r.u64()?, ruleset);
+        e.rng = GameRng::new(r.u64()?);
+        e.randomizer.decode(r)?;
+        for row in e.rows.iter_mut() {
+            *row = r.u16()?;
+        }
+        e.dropping = e.decode_block(r)?;
+        e.spawned = e.decode_block(r)?;
+        e.col = r.i32()?;
+        e.row = r.i32()?;
+        e.lowest_row = r.i32()?;
+        e.lock_frames = r.u32()?;
+        e.lock_resets = r.u32()?;
+        e.gravity = r.f64()?;
+        e.last_kick = match r.u8()? {
+            u8::MAX => None,
+            k => Some(k as usize),
+        };
+        // the queue is always full, spawn takes a block from it without asking.
+        if r.varint()? != Queue::MAX_PREVIEWS as u64 {
+            return Err("the queue is not full".to_string());
+        }
+        e.queue.clear();
+        for _ in 0..Queue::MAX_PREVIEWS {
+            let b = e.decode_block(r)?.ok_or("an empty block in the queue")?;
+            e.queue.push(b);
+        }
+        e.hold = e.decode_block(r)?;
+        e.hold_locked = r.bool()?;
+        e.lines = r.i32()?;
+        e.level = r.i32()?;
+        e.score = r.i32()?;
+        e.scoring = Scoring::decode(r)?;
+        e.pieces = r.u32()?;
+        e.garbage_rows = (r.u32()? as i32).min(Self::ROWS_COUNT - 1);
+        e.garbage_rng = GameRng::new(r.u64()?);
+        let field = Self::LEFT_EDGE_COL..Self::LEFT_EDGE_COL + Self::FIELD_COLS;
+        e.last_hole = match r.u8()? {
+            u8::MAX => None,
+            h if field.contains(&(h as i32)) => Some(h as i32),
+            _ => return Err("the hole of the garbage is off the field".to_string()),
+        };
+
+        // a board from a file or the network: without the ground and the walls nothing
+        // would stop a block, and a place far off would overflow.
+        if e.rows[0] != Self::GROUND_ROW_DATA
+            || e.rows[1..]
+                .iter()
+                .any(|r| r & Self::DEFAULT_ROW_DATA != Self::DEFAULT_ROW_DATA)
+        {
+            return Err("the board has lost its walls or ground".to_string());
+        }
+        let rows = 0..Self::ROWS_COUNT + Block::CELLS_COUNT;
+        if !(0..=Self::COLS_COUNT - Block::CELLS_COUNT).contains(&e.col)
+            || !rows.contains(&e.row)
+            || !rows.contains(&e.lowest_row)
+        {
+            return Err("the dropping block is off the board".to_string());
+        }
+        if let Some(b) = e.dropping {
+            if e.collide_at(e.col, e.row, b.data()) {
+                return Err("the dropping block is inside the stack".to_string());
+            }
+            e.shadow();
+        }
+        Ok(e)
+    }
+    fn encode_block(w: &mut Writer, b: Option<&Block>) {
+        match b {
+            Some(b) => {
+                w.u8(b.index() as u8);
+                w.u8(b.posture.index() as u8);
+            }
+            None => w.u8(u8::MAX),
+        }
+    }
+    fn decode_block(&self, r: &mut Reader) -> Result<Option<Block>, String> {
+        let index = match r.u8()? {
+            u8::MAX => return Ok(None),
+            i if (i as usize) < Block::BLOCKS_COUNT => i as usize,
+            i => return Err(format!("unknown block {}", i)),
+        };
+        let posture = Posture::from(r.u8()?);
+        Ok(Some(Block::new(index, posture, self.rotation.shapes())))
+    }
+
+    pub(crate) fn move_left(&mut self) -> bool {
+        self.shift(-1)
+    }
+    pub(crate) fn move_right(&mut self) -> bool {
+        self.shift(1)
+    }
+    pub(crate) fn soft_drop(&mut self) -> bool {
+        let fell = self.fall();
+        if fell {
+            self.score += Scoring::soft_drop(1);
+        }
+        fell
+    }
+    pub(crate) fn rotate_clockwise(&mut self) -> bool {
+        self.rotate(|b| b.posture.clockwise())
+    }
+    pub(crate) fn rotate_anticlockwise(&mut self) -> bool {
+        self.rotate(|b| b.posture.anticlockwise())
+    }
+    // fall down and freeze at once, whatever the lock delay.
+    pub(crate) fn hard_drop(&mut self) -> Status {
+        if self.dropping.is_none() {
+            return Status::NeedBlock;
+        }
+        self.score += Scoring::hard_drop(self.row - self.shadow_row);
+        if self.row != self.shadow_row {
+            self.last_kick = None;
+        }
+        self.row = self.shadow_row;
+        self.lowest_row = self.row;
+        self.lock();
+        Status::Freeze
+    }
+
+    // swap the dropping block with the held one, or with the next one when nothing is held.
+    pub(crate) fn hold_block(&mut self) -> bool {
+        let b = match self.dropping {
+            Some(b) if !self.hold_locked => b,
+            _ => return false,
+        };
+        let incoming = self.hold.unwrap_or(*self.next());
+        if self.collide_at(self.rotation.spawn_col(), Self::SPAWN_ROW, incoming.data()) {
+            return false;
+        }
+        if self.hold.is_none() {
+            self.queue.pop();
+            self.refill();
+        }
+        let mut held = b;
+        held.posture = Posture::default();
+        self.hold = Some(held);
+        self.hold_locked = true;
+        self.place(incoming)
+    }
+
+    fn place(&mut self, b: Block) -> bool {
+        self.dropping = Some(b);
+        self.spawned = Some(b);
+        self.row = Self::SPAWN_ROW;
+        self.lowest_row = self.row;
+        self.lock_frames = 0;
+        self.lock_resets = 0;
+        self.col = self.rotation.spawn_col();
+        self.gravity = 0.0;
+        self.last_kick = None;
+        self.shadow_row = 0;
+        self.shadow();
+
+        if self.collide_at(self.col, self.row, b.data()) {
+            return false;
+        }
+        if self.ruleset.gravity.g(self.level) >= GravityCurve::MAX {
+            self.row = self.shadow_row; // 20G, it is on the ground right away
+        }
+        true
+    }
+    fn refill(&mut self) {
+        while !self.queue.is_full() {
+            let b = self.generate();
+            self.queue.push(b);
+        }
+    }
+    fn generate(&mut self) -> Block {
+        let index = self.randomizer.next(&mut self.rng);
+        let posture = self.rotation.spawn_posture(&mut self.rng);
+        Block::new(index, posture, self.rotation.shapes())
+    }
+    fn fall(&mut self) -> bool {
+        match self.dropping {
+            Some(b) if !self.collide_at(self.col, self.row - 1, b.data()) => {
+                self.row -= 1;
+                self.last_kick = None;
+                if self.row < self.lowest_row {
+                    self.lowest_row = self.row;
+                    self.lock_frames = 0;
+                    self.lock_resets = 0;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+    fn shift(&mut self, dx: i32) -> bool {
+        match self.dropping {
+            Some(b) if !self.collide_at(self.col + dx, self.row, b.data()) => {
+                self.col += dx;
+                self.last_kick = None;
+                self.shadow();
+                self.moved();
+                true
+            }
+            _ => false,
+        }
+    }
+    fn rotate(&mut self, f: impl Fn(&mut Block)) -> bool {
+        if let Some(mut b) = self.dropping {
+            let from = b.posture;
+            f(&mut b);
+            let kicks = self.rotation.kicks(b.index(), from, b.posture);
+            for (kick, (dx, dy)) in kicks.iter().enumerate() {
+                if !self.collide_at(self.col + dx, self.row + dy, b.data()) {
+                    self.col += dx;
+                    self.row += dy;
+                    self.last_kick = Some(kick);
+                    self.dropping = Some(b);
+                    self.shadow();
+                    self.moved();
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    // a successful move or rotation on the ground restarts the lock delay.
+    fn moved(&mut self) {
+        if self.lock_frames > 0 {
+            self.lock_frames = 0;
+            self.lock_resets += 1;
+        }
+    }
+    fn on_ground(&self) -> bool {
+        self.dropping
+            .is_some_and(|b| self.collide_at(self.col, self.row - 1, b.data()))
+    }
+    fn lock(&mut self) {
+        if let (Some(spawned), Some(block)) = (self.spawned, self.dropping) {
+            self.last_lock = Some(Lock {
+                spawned,
+                spawn_col: self.rotation.spawn_col(),
+                block,
+                col: self.col,
+            });
+        }
+        self.pieces += 1;
+        // the lowest cell of the block, which is the row of its box less the empty rows
+        // at the bottom of it.
+        if let Some(b) = self.dropping {
+            let empty = b.data().trailing_zeros() as i32 / Block::CELLS_COUNT;
+            let bottom = self.row - (Block::CELLS_COUNT - 1 - empty);
+            if bottom > Self::VISIBLE_ROWS {
+                self.top_out = Some(TopOut::Lock);
+            }
+        }
+        let spin = self.t_spin();
+        let removed = self.freeze();
+        let perfect = removed > 0
+            && self
+                .rows
+                .iter()
+                .skip(1)
+                .all(|r| *r == Self::DEFAULT_ROW_DATA);
+        self.count(removed, spin, perfect);
+    }
+    fn count(&mut self, removed: i32, spin: Spin, perfect: bool) {
+        let (clear, points) = self.scoring.lock(removed, spin, perfect, self.level);
+        self.last_clear = clear;
+        self.score += points;
+        if removed > 0 {
+            self.lines += removed;
+            self.level = self.level.max(1 + self.lines / Self::LINES_PER_LEVEL);
+        }
+    }
+    // the 3-corner rule: a T which got to its place by a rotation, with three of
+    // the four corners around its center filled. it is a mini unless both corners
+    // it points to are filled, or the last kick of srs was needed.
+    fn t_spin(&self) -> Spin {
+        const LAST_KICK: usize = 4;
+        let (b, kick) = match (self.dropping, self.last_kick) {
+            (Some(b), Some(kick)) if b.index() == Block::T => (b, kick),
+            _ => return Spin::None,
+        };
+        // clockwise from the top-left, and the sides from the top, of the 3x3 box.
+        let corners = [(0, 0), (2, 0), (2, 2), (0, 2)];
+        let sides = [(1, 0), (2, 1), (1, 2), (0, 1)];
+
+        let filled: Vec<bool> = corners
+            .iter()
+            .map(|(x, y)| self.filled(self.col + x, self.row - y))
+            .collect();
+        if filled.iter().filter(|f| **f).count() < 3 {
+            return Spin::None;
+        }
+        let data = b.data();
+        let missing = sides
+            .iter()
+            .position(|(x, y)| data & (0x8000 >> (y * Block::CELLS_COUNT + x)) == 0)
+            .unwrap_or(0);
+        let pointing = (missing + 2) % 4;
+        if (filled[pointing] && filled[(pointing + 1) % 4]) || kick == LAST_KICK {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+    fn filled(&self, col: i32, row: i32) -> bool {
+        if row <= 0 || !(0..Self::COLS_COUNT).contains(&col) {
+            return true;
+        }
+        self.rows
+            .get(row as usize)
+            .is_some_and(|r| r & (0x8000 >> col) != 0)
+    }
+    fn shadow(&mut self) {
+        if let Some(block) = self.dropping {
+            self.shadow_row = self.row;
+            while !self.collide_at(self.col, self.shadow_row - 1, block.data()) {
+                self.shadow_row -= 1;
+            }
+        }
+    }
+    fn freeze(&mut self) -> i32 {
+        let mut removed = 0;
+        if let Some(b) = self.dropping.as_ref() {
+            let mut d = b.data();
+            for i in 0..Block::CELLS_COUNT {
+                // the block never goes past the top of the hidden rows, nor under the ground.
+                if let Some(r) = self.rows.get_mut((self.row - i) as usize) {
+                    *r |= (d & 0xF000) >> self.col;
+                }
+                d <<= Block::CELLS_COUNT;
+            }
+            self.hold_locked = false;
+
+            // remove the lines which are full
+            let v = self.rows.to_vec();
+            let garbage_rows = self.garbage_rows as usize;
+            let mut garbage_removed = 0;
+            let mut it = v
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(i, r)| {
+                    if **r == Self::GROUND_ROW_DATA {
+                        removed += 1;
+                        if *i <= garbage_rows {
+                            garbage_removed += 1;
+                        }
+                    }
+                    **r != Self::GROUND_ROW_DATA
+                })
+                .map(|(_, r)| *r);
+            for r in self.rows.iter_mut().skip(1) {
+                if *r == Self::DEFAULT_ROW_DATA {
+                    break;
+                }
+                *r = it.next().unwrap_or(Self::DEFAULT_ROW_DATA);
+            }
+            drop(it);
+            self.garbage_rows -= garbage_removed;
+        }
+        self.dropping.take();
+        removed
+    }
+    fn collide_at(&self, col: i32, row: i32, data: u16) -> bool {
+        // a kick may push the block past the walls, there is nothing but wall there.
+        if !(0..=Self::COLS_COUNT - Block::CELLS_COUNT).contains(&col) {
+            return true;
+        }
+        let mut pos_data = 0_u16;
+        for i in 0..Block::CELLS_COUNT {
+            let r = self
+                .rows
+                .get(std::cmp::max(row - i, 0) as usize)
+                .unwrap_or(&Self::DEFAULT_ROW_DATA);
+            pos_data <<= Block::CELLS_COUNT;
+            pos_data |= (r >> (Self::COLS_COUNT - col - Block::CELLS_COUNT)) & 0x000F;
+        }
+        pos_data & data > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotation::RotationKind;
+
+    fn engine(index: usize, rotation: RotationKind) -> Engine {
+        let mut ruleset = Ruleset::guideline();
+        ruleset.rotation = rotation;
+        let mut e = Engine::new(0, ruleset);
+        e.dropping = Some(Block::new(index, Posture::default(), e.rotation.shapes()));
+        e.row = 10;
+        e.shadow();
+        e
+    }
+
+    #[test]
+    fn srs_kicks_off_the_wall() {
+        // a vertical I against the right wall can not turn in place
+        let mut e = engine(Block::I, RotationKind::Srs);
+        e.rotate_clockwise();
+        while e.move_right() {}
+        let col = e.col;
+        assert!(e.rotate_clockwise());
+        assert_ne!(e.col, col);
+
+        let mut e = engine(Block::I, RotationKind::Classic);
+        while e.move_right() {}
+        assert!(!e.rotate_clockwise());
+    }
+
+    #[test]
+    fn clear_a_line() {
+        let mut e = engine(Block::I, RotationKind::Srs);
+        e.rows[1] = 0b1111_1111_0000_1111;
+        e.col = 8;
+        e.shadow();
+        e.hard_drop();
+        assert_eq!(e.lines(), 1);
+        assert_eq!(e.rows[1], Engine::DEFAULT_ROW_DATA);
+    }
+
+    #[test]
+    fn dig_out_the_garbage() {
+        let mut e = engine(Block::I, RotationKind::Srs);
+        e.rows[1] = 0b1110_0000_0001_1111;
+        assert!(e.insert_garbage(&[5, 5, 5]));
+        assert_eq!(e.rows[4], 0b1110_0000_0001_1111);
+        assert_eq!(e.garbage_rows(), 3);
+        // a vertical I down the holes.
+        e.rotate_clockwise();
+        e.col = 3;
+        e.shadow();
+        e.hard_drop();
+        assert_eq!(e.lines(), 3);
+        assert_eq!(e.garbage_rows(), 0);
+        assert_eq!(e.rows[1], 0b1110_0100_0001_1111);
+    }
+
+    #[test]
+    fn garbage_pushes_the_block_up() {
+        let mut e = engine(Block::O, RotationKind::Srs);
+        e.row = 3;
+        e.shadow();
+        assert_eq!(e.shadow_row(), 2);
+        // the block is not in the way of one row, it is of two.
+        assert!(e.insert_garbage(&[12]));
+        assert_eq!((e.row(), e.shadow_row()), (3, 3));
+        assert!(e.insert_garbage(&[12]));
+        assert_eq!((e.row(), e.shadow_row()), (4, 4));
+
+        // into the hidden rows, then over them.
+        let mut e = engine(Block::O, RotationKind::Srs);
+        e.row = Engine::SPAWN_ROW;
+        e.shadow();
+        assert!(e.insert_garbage(&[3; 37]));
+        assert_eq!(e.top_out(), None);
+        assert!(!e.insert_garbage(&[3; 2]));
+        assert_eq!(e.top_out(), Some(TopOut::Garbage));
+    }
+
+    #[test]
+    fn lock_out_and_block_out() {
+        // the stack goes on in the hidden rows, a block locked all in them loses.
+        let mut e = engine(Block::O, RotationKind::Srs);
+        e.rows[1..=Engine::VISIBLE_ROWS as usize]
+            .iter_mut()
+            .for_each(|r| *r = 0b1111_1111_1110_0111);
+        e.row = Engine::VISIBLE_ROWS + 2;
+        e.shadow();
+        e.hard_drop();
+        assert_eq!(
+            e.rows[Engine::VISIBLE_ROWS as usize + 1],
+            0b1110_0001_1000_0111
+        );
+        assert_eq!(e.top_out(), Some(TopOut::Lock));
+        assert!(e.step(&[]).is_none());
+
+        let mut e = engine(Block::O, RotationKind::Srs);
+        e.rows[1..Engine::ROWS_COUNT as usize]
+            .iter_mut()
+            .for_each(|r| *r = 0b1111_1111_1110_0111);
+        e.dropping = None;
+        assert!(e.step(&[]).is_none());
+        assert_eq!(e.top_out(), Some(TopOut::Block));
+    }
+
+    #[test]
+    fn hold_once_per_block() {
+        let mut e = engine(Block::T, RotationKind::Srs);
+        let next = e.next().index();
+        assert!(e.hold_block());
+        assert_eq!(e.dropping().map(|b| b.index()), Some(next));
+        assert_eq!(e.hold().map(|b| b.index()), Some(Block::T));
+        assert!(!e.hold_block());
+
+        e.hard_drop();
+        assert!(e.spawn());
+        assert!(e.hold_block());
+        assert_eq!(e.dropping().map(|b| b.index()), Some(Block::T));
+    }
+
+    #[test]
+    fn lock_delay_resets_on_move() {
+        let mut e = engine(Block::T, RotationKind::Srs);
+        while e.soft_drop() {}
+        let frames = |e: &mut Engine, n| (0..n).map(|_| e.frame()).last().unwrap();
+        assert!(matches!(frames(&mut e, 24), Status::Dropping));
+        assert!(e.move_left());
+        assert!(matches!(frames(&mut e, 24), Status::Dropping));
+        assert!(matches!(frames(&mut e, 6), Status::Freeze));
+
+        e.ruleset.lock_delay = LockDelay::None;
+        assert!(e.spawn());
+        while e.soft_drop() {}
+        assert!(matches!(frames(&mut e, 59), Status::Dropping));
+        assert!(matches!(e.frame(), Status::Freeze));
+
+        // a hard drop does not wait for gravity.
+        assert!(e.spawn());
+        assert!(matches!(e.hard_drop(), Status::Freeze));
+    }
+
+    #[test]
+    fn twenty_g_lands_on_spawn() {
+        let mut e = engine(Block::T, RotationKind::Srs);
+        e.ruleset.gravity = GravityCurve::Twenty;
+        assert!(e.spawn());
+        assert_eq!(e.row(), e.shadow_row());
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut e = engine(Block::T, RotationKind::Srs);
+        // a slot for a T pointing down, with an overhang on its left
+        e.rows[1] = 0b1111_1111_1011_1111;
+        e.rows[2] = 0b1111_1111_0001_1111;
+        e.rows[3] = 0b1110_0000_1000_0111;
+        e.dropping = Some(Block::new(Block::T, Posture::from(2), e.rotation.shapes()));
+        e.col = 8;
+        e.row = 3;
+        e.last_kick = Some(0);
+        e.lock();
+        let clear = e.last_clear().copied().unwrap();
+        assert_eq!((clear.lines, clear.spin), (2, Spin::Full));
+        assert_eq!(clear.name(), "T-Spin Double");
+
+        // the same slot without the overhang is no spin at all
+        let mut e = engine(Block::T, RotationKind::Srs);
+        e.rows[1] = 0b1111_1111_1011_1111;
+        e.rows[2] = 0b1111_1111_0001_1111;
+        e.dropping = Some(Block::new(Block::T, Posture::from(2), e.rotation.shapes()));
+        e.col = 8;
+        e.row = 3;
+        e.last_kick = Some(0);
+        e.lock();
+        assert_eq!(e.last_clear().map(|c| c.spin), Some(Spin::None));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut e = Engine::new(3, Ruleset::guideline());
+        for frame in 0..30 {
+            let action = [Action::MoveLeft, Action::HardDrop, Action::Hold][frame % 3];
+            e.step(&[action]);
+        }
+        assert_eq!(e.top_out(), None);
+        let mut w = Writer::default();
+        e.encode(&mut w);
+        let bytes = w.into_bytes();
+        let mut loaded = Engine::decode(&mut Reader::new(&bytes)).unwrap();
+
+        for _ in 0..100 {
+            e.step(&[Action::RotateCW, Action::HardDrop]);
+            loaded.step(&[Action::RotateCW, Action::HardDrop]);
+        }
+        assert_eq!(loaded.rows(), e.rows());
+        assert_eq!(loaded.score(), e.score());
+        assert_eq!(loaded.next().index(), e.next().index());
+    }
+
+    #[test]
+    fn corrupt_boards() {
+        let decode = |e: &Engine| {
+            let mut w = Writer::default();
+            e.encode(&mut w);
+            Engine::decode(&mut Reader::new(&w.into_bytes()))
+        };
+        let mut e = Engine::new(3, Ruleset::guideline());
+        e.step(&[]);
+        assert!(decode(&e).is_ok());
+        e.rows[0] = Engine::DEFAULT_ROW_DATA;
+        assert!(decode(&e).is_err());
+        e.rows[0] = Engine::GROUND_ROW_DATA;
+        e.rows[5] = 0;
+        assert!(decode(&e).is_err());
+        e.rows[5] = Engine::DEFAULT_ROW_DATA;
+        e.row = i32::MAX;
+        assert!(decode(&e).is_err());
+        e.row = Engine::SPAWN_ROW;
+        e.col = -7;
+        assert!(decode(&e).is_err());
+        e.col = e.rotation.spawn_col();
+        assert!(decode(&e).is_ok());
+        // the next spawn would find no block.
+        e.queue.clear();
+        assert!(decode(&e).is_err());
+        e.refill();
+        assert!(decode(&e).is_ok());
+    }
+
+    #[test]
+    fn garbage_hole_off_the_field() {
+        let decode = |e: &Engine| {
+            let mut w = Writer::default();
+            e.encode(&mut w);
+            Engine::decode(&mut Reader::new(&w.into_bytes()))
+        };
+        let mut e = Engine::new(3, Ruleset::guideline());
+        e.step(&[]);
+        let holes = e.garbage_holes(1, 0);
+        assert!(e.insert_garbage(&holes));
+        assert!(decode(&e).is_ok());
+        e.last_hole = Some(200);
+        assert!(decode(&e).is_err());
+        e.last_hole = Some(Engine::LEFT_EDGE_COL - 1);
+        assert!(decode(&e).is_err());
+        e.last_hole = Some(Engine::LEFT_EDGE_COL + Engine::FIELD_COLS);
+        assert!(decode(&e).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// -- finesse.rs --
+
+use crate::{
+    blocks::{Block, Posture},
+    codec::{Reader, Writer},
+    engine::{Engine, Lock},
+    input::Action,
+};
+use std::collections::{HashSet, VecDeque};
+
+// --
+
+// the cells of a block at a column, row by row from the top, without the empty rows above.
+// the postures with the same cells, e.g. the two flat ones of an I, land the same.
+fn footprint(data: u16, col: i32) -> [u16; 4] {
+    let mut rows = [0; 4];
+    let mut n = 0;
+    for i in 0..Block::CELLS_COUNT {
+        let row = ((data << (i * Block::CELLS_COUNT)) & 0xF000) >> col;
+        if row != 0 || n > 0 {
+            rows[n] = row;
+            n += 1;
+        }
+    }
+    rows
+}
+
+fn fits(data: u16, col: i32) -> bool {
+    (0..=Engine::COLS_COUNT - Block::CELLS_COUNT).contains(&col)
+        && footprint(data, col)
+            .iter()
+            .all(|r| r & Engine::DEFAULT_ROW_DATA == 0)
+}
+
+// the fewest keys which bring a block from where it came in to where it locked, on an empty
+// board: a tap, a direction held to the wall or a rotation is one key each.
+pub(crate) fn minimal_keys(lock: &Lock) -> u32 {
+    let target = footprint(lock.block.data(), lock.col);
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([(lock.spawned, lock.spawn_col, 0)]);
+    while let Some((b, col, keys)) = queue.pop_front() {
+        if footprint(b.data(), col) == target {
+            return keys;
+        }
+        if !seen.insert((b.posture.index(), col)) {
+            continue;
+        }
+        let wall = |step: i32| {
+            let mut c = col;
+            while fits(b.data(), c + step) {
+                c += step;
+            }
+            c
+        };
+        let turned = |f: fn(&mut Posture)| {
+            let mut t = b;
+            f(&mut t.posture);
+            t
+        };
+        let next = [
+            (b, col - 1),
+            (b, col + 1),
+            (b, wall(-1)),
+            (b, wall(1)),
+            (turned(Posture::clockwise), col),
+            (turned(Posture::anticlockwise), col),
+        ];
+        for (b, col) in next {
+            if fits(b.data(), col) {
+                queue.push_back((b, col, keys + 1));
+            }
+        }
+    }
+    // only a kick gets it there, no fault can be told.
+    0
+}
+
+// --
+
+// the keys pressed for every block, against the fewest it would have taken. a block soft
+// dropped may have been tucked under the stack, it is not counted.
+#[derive(Default)]
+pub(crate) struct Finesse {
+    keys: u32,
+    soft_dropped: bool,
+    faults: u32,
+}
+
+impl Finesse {
+    pub(crate) fn faults(&self) -> u32 {
+        self.faults
+    }
+    // the keys pressed and the ones repeated while held, before the frame runs.
+    pub(crate) fn actions(&mut self, pressed: &[Action], repeated: &[Action]) {
+        for action in pressed {
+            match action {
+                Action::MoveLeft | Action::MoveRight | Action::RotateCW | Action::RotateCCW => {
+                    self.keys += 1
+                }
+                Action::SoftDrop => self.soft_dropped = true,
+                // another block comes in.
+                Action::Hold => self.keys = 0,
+                _ => {}
+            }
+        }
+        if repeated.contains(&Action::SoftDrop) {
+            self.soft_dropped = true;
+        }
+    }
+    pub(crate) fn locked(&mut self, lock: &Lock) {
+        if !self.soft_dropped {
+            self.faults += self.keys.saturating_sub(minimal_keys(lock));
+        }
+        self.keys = 0;
+        self.soft_dropped = false;
+    }
+    pub(crate) fn encode(&self, w: &mut Writer) {
+        w.u32(self.keys);
+        w.bool(self.soft_dropped);
+        w.u32(self.faults);
+    }
+    pub(crate) fn decode(r: &mut Reader) -> Result<Self, String> {
+        Ok(Self {
+            keys: r.u32()?,
+            soft_dropped: r.bool()?,
+            faults: r.u32()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotation::RotationKind;
+
+    fn lock(index: usize, turns: u8, col: i32) -> Lock {
+        let shapes = RotationKind::Srs.create().shapes();
+        Lock {
+            spawned: Block::new(index, Posture::default(), shapes),
+            spawn_col: 6,
+            block: Block::new(index, Posture::from(turns), shapes),
+            col,
+        }
+    }
+
+    #[test]
+    fn fewest_keys() {
+        assert_eq!(minimal_keys(&lock(Block::O, 0, 6)), 0);
+        assert_eq!(minimal_keys(&lock(Block::T, 1, 6)), 1);
+        assert_eq!(minimal_keys(&lock(Block::T, 2, 6)), 2);
+        assert_eq!(minimal_keys(&lock(Block::T, 3, 6)), 1);
+        // held to the left wall.
+        assert_eq!(minimal_keys(&lock(Block::O, 0, 2)), 1);
+        assert_eq!(minimal_keys(&lock(Block::T, 1, 5)), 2);
+    }
+
+    #[test]
+    fn faults() {
+        let mut f = Finesse::default();
+        let taps = [Action::MoveLeft, Action::MoveLeft, Action::MoveLeft];
+        f.actions(&taps, &[]);
+        f.locked(&lock(Block::O, 0, 2));
+        assert_eq!(f.faults(), 2);
+        f.actions(&taps, &[Action::SoftDrop]);
+        f.locked(&lock(Block::O, 0, 2));
+        assert_eq!(f.faults(), 2);
+    }
+}
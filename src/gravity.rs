@@ -0,0 +1,57 @@
+// -- gravity.rs --
+
+// --
+
+// how fast a block falls at every level, in G: rows per frame at 60 frames a second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum GravityCurve {
+    // one row a second at level 1, and 10% faster every level after it.
+    Classic,
+    // (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds a row.
+    Guideline,
+    // every block lands as soon as it appears.
+    Twenty,
+}
+
+impl GravityCurve {
+    pub(crate) const FRAMES_PER_SECOND: u32 = 60;
+    pub(crate) const MAX: f64 = 20.0;
+    pub(crate) const ALL: [Self; 3] = [Self::Classic, Self::Guideline, Self::Twenty];
+
+    pub(crate) fn g(self, level: i32) -> f64 {
+        let level = level.max(1) as f64;
+        let seconds = match self {
+            Self::Classic => 0.9_f64.powf(level - 1.0),
+            Self::Guideline => (0.8 - (level - 1.0) * 0.007).max(0.0).powf(level - 1.0),
+            Self::Twenty => return Self::MAX,
+        };
+        if seconds <= 0.0 {
+            return Self::MAX;
+        }
+        (1.0 / (seconds * Self::FRAMES_PER_SECOND as f64)).min(Self::MAX)
+    }
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Guideline => "guideline",
+            Self::Twenty => "20g",
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves() {
+        assert!((GravityCurve::Classic.g(1) - 1.0 / 60.0).abs() < 1e-9);
+        assert!((GravityCurve::Guideline.g(2) - 1.0 / (0.793 * 60.0)).abs() < 1e-9);
+        assert!(GravityCurve::Guideline.g(10) < GravityCurve::Guideline.g(11));
+        assert_eq!(GravityCurve::Guideline.g(20), GravityCurve::MAX);
+        assert_eq!(GravityCurve::Twenty.g(1), GravityCurve::MAX);
+    }
+}
@@ -0,0 +1,437 @@
+// -- input.rs --
+
+use crate::{engine::Engine, gravity::GravityCurve};
+use fltk::enums::Key;
+use std::{fmt::Write as _, fs, io, path::Path};
+
+// --
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCW,
+    RotateCCW,
+    Hold,
+    Pause,
+    Save,
+}
+
+impl Action {
+    pub(crate) const ALL: [Self; 9] = [
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::SoftDrop,
+        Self::HardDrop,
+        Self::RotateCW,
+        Self::RotateCCW,
+        Self::Hold,
+        Self::Pause,
+        Self::Save,
+    ];
+
+    // the name in the config file.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "move_left",
+            Self::MoveRight => "move_right",
+            Self::SoftDrop => "soft_drop",
+            Self::HardDrop => "hard_drop",
+            Self::RotateCW => "rotate_cw",
+            Self::RotateCCW => "rotate_ccw",
+            Self::Hold => "hold",
+            Self::Pause => "pause",
+            Self::Save => "save",
+        }
+    }
+    // the name in the help board.
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "left",
+            Self::MoveRight => "right",
+            Self::SoftDrop => "down",
+            Self::HardDrop => "drop",
+            Self::RotateCW => "clockwise",
+            Self::RotateCCW => "anticlockwise",
+            Self::Hold => "hold",
+            Self::Pause => "pause",
+            Self::Save => "save",
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| a.name() == name)
+    }
+    pub(crate) fn index(self) -> usize {
+        Self::ALL.iter().position(|a| *a == self).unwrap()
+    }
+}
+
+// --
+
+// keys with no printable char, by the names used in the config file.
+const KEY_NAMES: [(&str, Key); 28] = [
+    ("Space", Key::from_char(' ')),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Escape", Key::Escape),
+    ("Enter", Key::Enter),
+    ("Tab", Key::Tab),
+    ("BackSpace", Key::BackSpace),
+    ("ShiftL", Key::ShiftL),
+    ("ShiftR", Key::ShiftR),
+    ("ControlL", Key::ControlL),
+    ("ControlR", Key::ControlR),
+    ("AltL", Key::AltL),
+    ("AltR", Key::AltR),
+    ("CapsLock", Key::CapsLock),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+];
+
+pub(crate) fn key_by_name(name: &str) -> Option<Key> {
+    if let Some((_, k)) = KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return Some(*k);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        // fltk reports the letter keys in lowercase, whatever the caps lock or shift
+        (Some(c), None) if c.is_ascii_graphic() => Some(Key::from_char(c.to_ascii_lowercase())),
+        _ => None,
+    }
+}
+
+pub(crate) fn key_name(key: Key) -> String {
+    match KEY_NAMES.iter().find(|(_, k)| *k == key) {
+        Some((n, _)) => n.to_string(),
+        None => match key.to_char() {
+            Some(c) if c.is_ascii_graphic() => c.to_string(),
+            _ => format!("#{:x}", key.bits()),
+        },
+    }
+}
+
+// --
+
+// which physical keys trigger which actions, several keys may share one action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Bindings {
+    keys: Vec<(Key, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::from_names(&[
+            ("a", Action::MoveLeft),
+            ("Left", Action::MoveLeft),
+            ("d", Action::MoveRight),
+            ("Right", Action::MoveRight),
+            ("s", Action::SoftDrop),
+            ("Down", Action::SoftDrop),
+            ("Space", Action::HardDrop),
+            ("e", Action::RotateCW),
+            ("Up", Action::RotateCW),
+            ("q", Action::RotateCCW),
+            ("c", Action::Hold),
+            ("Escape", Action::Pause),
+            ("F5", Action::Save),
+        ])
+    }
+}
+
+impl Bindings {
+    pub(crate) const FILE_NAME: &'static str = "keys.conf";
+    // the keys of the two players of a versus, each on a side of the keyboard.
+    pub(crate) const VERSUS_FILE_NAMES: [&'static str; 2] = ["keys-1.conf", "keys-2.conf"];
+
+    fn from_names(keys: &[(&str, Action)]) -> Self {
+        Self {
+            keys: keys
+                .iter()
+                .map(|(n, a)| (key_by_name(n).unwrap(), *a))
+                .collect(),
+        }
+    }
+    pub(crate) fn versus(player: usize) -> Self {
+        match player {
+            0 => Self::from_names(&[
+                ("a", Action::MoveLeft),
+                ("d", Action::MoveRight),
+                ("s", Action::SoftDrop),
+                ("Space", Action::HardDrop),
+                ("w", Action::RotateCW),
+                ("q", Action::RotateCCW),
+                ("ShiftL", Action::Hold),
+                ("Escape", Action::Pause),
+            ]),
+            _ => Self::from_names(&[
+                ("Left", Action::MoveLeft),
+                ("Right", Action::MoveRight),
+                ("Down", Action::SoftDrop),
+                ("Enter", Action::HardDrop),
+                ("Up", Action::RotateCW),
+                ("ShiftR", Action::RotateCCW),
+                ("ControlR", Action::Hold),
+                ("F1", Action::Pause),
+            ]),
+        }
+    }
+
+    pub(crate) fn action(&self, key: Key) -> Option<Action> {
+        self.keys.iter().find(|(k, _)| *k == key).map(|(_, a)| *a)
+    }
+    pub(crate) fn keys(&self, action: Action) -> impl Iterator<Item = Key> + '_ {
+        self.keys
+            .iter()
+            .filter(move |(_, a)| *a == action)
+            .map(|(k, _)| *k)
+    }
+
+    // lines of "action = key, key", '#' starts a comment.
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected \"action = keys\"", i + 1))?;
+            let action = Action::by_name(name.trim())
+                .ok_or_else(|| format!("line {}: unknown action {}", i + 1, name.trim()))?;
+            for k in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+                let key =
+                    key_by_name(k).ok_or_else(|| format!("line {}: unknown key {}", i + 1, k))?;
+                keys.retain(|(old, _)| *old != key);
+                keys.push((key, action));
+            }
+        }
+        Ok(Self { keys })
+    }
+    pub(crate) fn to_text(&self) -> String {
+        let mut text = String::from("# action = key, key\n");
+        for action in Action::ALL.iter() {
+            let keys: Vec<String> = self.keys(*action).map(key_name).collect();
+            let _ = writeln!(text, "{} = {}", action.name(), keys.join(", "));
+        }
+        text
+    }
+    // the bindings of the file, which is written with the defaults if it does not exist yet.
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        Self::load_or(path, Self::default())
+    }
+    pub(crate) fn load_or(path: &Path, defaults: Self) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let bindings = defaults;
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(path, bindings.to_text())?;
+                Ok(bindings)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    // for the help board, one action a line.
+    pub(crate) fn help(&self) -> String {
+        Action::ALL
+            .iter()
+            .filter_map(|a| {
+                let keys: Vec<String> = self.keys(*a).map(key_name).collect();
+                (!keys.is_empty()).then(|| format!("{}: {}", keys.join(", "), a.label()))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// --
+
+// how held keys repeat, in ms of the game timer whatever the key repeat of the os is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Handling {
+    // delayed auto shift: how long a move key is held before it repeats.
+    pub(crate) das_ms: u32,
+    // auto repeat rate: between two repeated moves, 0 shifts to the wall at once.
+    pub(crate) arr_ms: u32,
+    // between two rows of a held soft drop, 0 drops to the ground at once.
+    pub(crate) soft_drop_ms: u32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Self {
+            das_ms: 167,
+            arr_ms: 33,
+            soft_drop_ms: 50,
+        }
+    }
+}
+
+fn frames(ms: u32) -> u32 {
+    ms * GravityCurve::FRAMES_PER_SECOND / 1000
+}
+
+// the keys which are down, and the moves they repeat frame after frame.
+pub(crate) struct AutoRepeat {
+    handling: Handling,
+    held: Vec<(Key, Action)>,
+    // the direction which shifts, and for how many frames.
+    shift: Option<(Action, u32)>,
+    soft_drop: Option<u32>,
+}
+
+impl AutoRepeat {
+    pub(crate) fn new(handling: Handling) -> Self {
+        Self {
+            handling,
+            held: Vec::new(),
+            shift: None,
+            soft_drop: None,
+        }
+    }
+    fn is_held(&self, action: Action) -> bool {
+        self.held.iter().any(|(_, a)| *a == action)
+    }
+    // false if the key is down already, i.e. the os repeats it.
+    pub(crate) fn press(&mut self, key: Key, action: Action) -> bool {
+        if self.held.iter().any(|(k, _)| *k == key) {
+            return false;
+        }
+        self.held.push((key, action));
+        match action {
+            Action::MoveLeft | Action::MoveRight => self.shift = Some((action, 0)),
+            Action::SoftDrop => self.soft_drop = Some(0),
+            _ => {}
+        }
+        true
+    }
+    pub(crate) fn release(&mut self, key: Key) {
+        self.held.retain(|(k, _)| *k != key);
+        // the other direction, if it is still down, charges again.
+        if let Some((a, _)) = self.shift {
+            if !self.is_held(a) {
+                self.shift = self
+                    .held
+                    .iter()
+                    .rev()
+                    .find(|(_, a)| matches!(a, Action::MoveLeft | Action::MoveRight))
+                    .map(|(_, a)| (*a, 0));
+            }
+        }
+        if !self.is_held(Action::SoftDrop) {
+            self.soft_drop = None;
+        }
+    }
+    // e.g. on a pause. the pause key stays down until it is let go, or the os repeating it
+    // would resume the game at once.
+    pub(crate) fn release_all(&mut self) {
+        self.held.retain(|(_, a)| *a == Action::Pause);
+        self.shift = None;
+        self.soft_drop = None;
+    }
+    // the moves repeated in this frame.
+    pub(crate) fn frame(&mut self) -> Vec<Action> {
+        let mut actions = Vec::new();
+        if let Some((a, n)) = self.shift.as_mut() {
+            *n += 1;
+            let das = frames(self.handling.das_ms);
+            let arr = frames(self.handling.arr_ms);
+            if *n >= das {
+                if arr == 0 {
+                    actions.extend(std::iter::repeat_n(*a, Engine::COLS_COUNT as usize));
+                } else if (*n - das).is_multiple_of(arr) {
+                    actions.push(*a);
+                }
+            }
+        }
+        if let Some(n) = self.soft_drop.as_mut() {
+            *n += 1;
+            match frames(self.handling.soft_drop_ms) {
+                0 => actions.extend(std::iter::repeat_n(
+                    Action::SoftDrop,
+                    Engine::ROWS_COUNT as usize,
+                )),
+                sd if n.is_multiple_of(sd) => actions.push(Action::SoftDrop),
+                _ => {}
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bindings = Bindings::default();
+        assert_eq!(Bindings::parse(&bindings.to_text()), Ok(bindings));
+    }
+
+    #[test]
+    fn parse_keys() {
+        let b = Bindings::parse("move_left = j, left # vim\nhold = ShiftL\n").unwrap();
+        assert_eq!(b.action(Key::from_char('j')), Some(Action::MoveLeft));
+        assert_eq!(b.action(Key::Left), Some(Action::MoveLeft));
+        assert_eq!(b.action(Key::ShiftL), Some(Action::Hold));
+        assert_eq!(b.action(Key::from_char('a')), None);
+        assert!(Bindings::parse("jump = x").is_err());
+    }
+
+    #[test]
+    fn das_then_arr() {
+        let handling = Handling {
+            das_ms: 100,
+            arr_ms: 50,
+            soft_drop_ms: 0,
+        };
+        let (left, right) = (Key::from_char('a'), Key::from_char('d'));
+        let mut r = AutoRepeat::new(handling);
+        assert!(r.press(left, Action::MoveLeft));
+        assert!(!r.press(left, Action::MoveLeft));
+        let moves: Vec<usize> = (0..12).map(|_| r.frame().len()).collect();
+        assert_eq!(moves, [0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 1]);
+
+        // the newer direction wins, and the older one charges again when it is let go.
+        assert!(r.press(right, Action::MoveRight));
+        assert_eq!(r.frame(), []);
+        r.release(right);
+        assert!((0..5).all(|_| r.frame().is_empty()));
+        assert_eq!(r.frame(), [Action::MoveLeft]);
+        r.release(left);
+        assert_eq!(r.frame(), []);
+    }
+
+    #[test]
+    fn pause_key_stays_down() {
+        let mut r = AutoRepeat::new(Handling::default());
+        assert!(r.press(Key::Escape, Action::Pause));
+        assert!(r.press(Key::Left, Action::MoveLeft));
+        r.release_all();
+        // the os repeats the held pause key, it does not resume.
+        assert!(!r.press(Key::Escape, Action::Pause));
+        assert!(r.press(Key::Left, Action::MoveLeft));
+        r.release(Key::Escape);
+        assert!(r.press(Key::Escape, Action::Pause));
+    }
+}
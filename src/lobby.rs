@@ -0,0 +1,157 @@
+// -- lobby.rs --
+
+use crate::{
+    net::{Announcement, Announcer, Connection, Game, Lobby},
+    online::OnlineWindow,
+    options::Options,
+    window::with,
+};
+use fltk::{
+    browser::HoldBrowser,
+    button::Button,
+    prelude::{BrowserExt, GroupExt, WidgetBase, WidgetExt, WindowExt},
+    window::DoubleWindow,
+};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+// --
+
+// the games hosted on the network, heard of as they are announced. one is joined by a
+// double click, or another one is hosted.
+pub(crate) struct LobbyWindow {
+    wind: DoubleWindow,
+    list: HoldBrowser,
+    lobby: Option<Lobby>,
+    // the games on the lines of the list.
+    shown: Vec<Game>,
+    options: Options,
+    // the game joined or hosted from here, the lobby is hidden meanwhile.
+    game: Option<Rc<RefCell<OnlineWindow>>>,
+    me: Weak<RefCell<Self>>,
+}
+
+impl LobbyWindow {
+    const POLL_SECONDS: f64 = 0.25;
+    const COLUMNS: [i32; 4] = [150, 100, 100, 60];
+
+    fn build(options: &Options, me: Weak<RefCell<Self>>) -> Self {
+        let mut wind = DoubleWindow::default().with_size(560, 330).center_screen();
+        let mut list = HoldBrowser::new(10, 10, 540, 270, "");
+        list.set_column_widths(&Self::COLUMNS);
+        let mut join = Button::new(350, 290, 95, 30, "Join");
+        let mut host = Button::new(455, 290, 95, 30, "Host");
+        wind.end();
+
+        let m = me.clone();
+        list.set_callback(move |_| {
+            if fltk::app::event_clicks() {
+                Self::join(&m);
+            }
+        });
+        let m = me.clone();
+        join.set_callback(move |_| Self::join(&m));
+        let m = me.clone();
+        host.set_callback(move |_| {
+            with(&m, |t| t.host());
+        });
+        wind.set_label("Tetris - lobby");
+        wind.show();
+
+        let lobby = match Lobby::listen(Announcer::DISCOVERY_PORT) {
+            Ok(lobby) => {
+                if let Ok(port) = lobby.port() {
+                    wind.set_label(&format!("Tetris - lobby - listening on port {}", port));
+                }
+                Some(lobby)
+            }
+            Err(e) => {
+                eprintln!("port {}: {}", Announcer::DISCOVERY_PORT, e);
+                list.add("No games can be heard of, another lobby may be open.");
+                None
+            }
+        };
+        Self {
+            wind,
+            list,
+            lobby,
+            shown: Vec::new(),
+            options: options.clone(),
+            game: None,
+            me,
+        }
+    }
+    pub(crate) fn new(options: &Options) -> Rc<RefCell<Self>> {
+        let lobby = Rc::new_cyclic(|me| RefCell::new(Self::build(options, me.clone())));
+        lobby.borrow_mut().start();
+        lobby
+    }
+    // the list is kept up to date for as long as the lobby is open.
+    fn start(&mut self) {
+        let me = self.me.clone();
+        fltk::app::add_timeout3(Self::POLL_SECONDS, move |handle| {
+            if with(&me, |t| t.poll()).is_some() {
+                fltk::app::repeat_timeout3(Self::POLL_SECONDS, handle);
+            }
+        });
+    }
+    fn poll(&mut self) {
+        // the same game stays selected.
+        let selected = self.selected().map(|g| g.announcement.id);
+        let lobby = match self.lobby.as_mut() {
+            Some(lobby) => lobby,
+            None => return,
+        };
+        if !lobby.poll() {
+            return;
+        }
+        self.shown = lobby.games().to_vec();
+        self.list.clear();
+        for (i, game) in self.shown.iter().enumerate() {
+            let a = &game.announcement;
+            self.list.add(&format!(
+                "{}\t{}\t{}\t{}/{}\t{}",
+                a.name,
+                a.mode,
+                a.ruleset,
+                a.players,
+                Announcement::MAX_PLAYERS,
+                game.address
+            ));
+            if Some(a.id) == selected {
+                self.list.select(i as i32 + 1);
+            }
+        }
+    }
+    fn selected(&self) -> Option<&Game> {
+        let line = self.list.value();
+        self.shown.get((line as usize).checked_sub(1)?)
+    }
+    fn join(me: &Weak<RefCell<Self>>) {
+        let refused = with(me, |t| {
+            let game = t.selected()?.clone();
+            if game.announcement.players >= Announcement::MAX_PLAYERS {
+                return Some(format!("The game of {} is full.", game.announcement.name));
+            }
+            // the window tells when the game cannot be joined.
+            let mut options = t.options.clone();
+            options.join = Some(game.address.to_string());
+            t.open(options);
+            None
+        });
+        if let Some(Some(refused)) = refused {
+            fltk::dialog::message_default(&refused);
+        }
+    }
+    fn host(&mut self) {
+        let mut options = self.options.clone();
+        options.host = Some(Connection::DEFAULT_PORT);
+        self.open(options);
+    }
+    fn open(&mut self, options: Options) {
+        self.game = Some(OnlineWindow::new(&options));
+        self.wind.hide();
+    }
+}
@@ -4,15 +4,51 @@
 
 // --
 
+mod attack;
 mod blocks;
 mod boards;
+mod codec;
+mod config;
+mod engine;
+mod finesse;
+mod gravity;
+mod input;
+mod lobby;
+mod mode;
+mod net;
+mod online;
+mod options;
+mod queue;
+mod randomizer;
+mod replay;
+mod rng;
+mod rotation;
+mod ruleset;
+mod save;
+mod scoreboard;
+mod scores;
+mod scoring;
 mod stage;
 mod tetris;
+mod versus;
+mod window;
 
 // --
 
 fn main() {
     let app = fltk::app::App::default();
-    let _tetris = tetris::TetrisWindow::new_box();
-    app.run().unwrap();
+    let options = options::Options::from_args();
+    if options.lobby {
+        let _lobby = lobby::LobbyWindow::new(&options);
+        app.run().unwrap();
+    } else if options.host.is_some() || options.join.is_some() {
+        let _online = online::OnlineWindow::new(&options);
+        app.run().unwrap();
+    } else if let Some(rounds) = options.versus {
+        let _versus = versus::VersusWindow::new(&options, rounds);
+        app.run().unwrap();
+    } else {
+        let _tetris = tetris::TetrisWindow::new(&options);
+        app.run().unwrap();
+    }
 }
@@ -0,0 +1,220 @@
+// -- mode.rs --
+
+use crate::{engine::Engine, gravity::GravityCurve, scores::Entry};
+
+// --
+
+// what a game is played for, and when it is over besides booming.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Mode {
+    // endless, for the score.
+    #[default]
+    Marathon,
+    // as fast as possible up to a number of lines.
+    Sprint {
+        lines: i32,
+    },
+    // the highest score before the time runs out.
+    Ultra {
+        seconds: u32,
+    },
+    // as fast as possible through rows of garbage, every next hole moves with a chance of
+    // `change` percent.
+    Dig {
+        rows: i32,
+        change: u32,
+    },
+    // as long as possible, with garbage rising faster and faster.
+    Survival,
+}
+
+impl Mode {
+    pub(crate) const SPRINT_LINES: [i32; 3] = [20, 40, 100];
+    pub(crate) const DEFAULT_SPRINT_LINES: i32 = 40;
+    pub(crate) const ULTRA_SECONDS: std::ops::RangeInclusive<u32> = 10..=3600;
+    pub(crate) const DEFAULT_ULTRA_SECONDS: u32 = 120;
+    // a block still comes in over the highest of them.
+    pub(crate) const DIG_ROWS: std::ops::RangeInclusive<i32> = 1..=15;
+    pub(crate) const DEFAULT_DIG_ROWS: i32 = 10;
+    // the frames before the first row of a survival rises, every next one comes a bit
+    // sooner, down to a second.
+    const SURVIVAL_FIRST: u32 = 10 * GravityCurve::FRAMES_PER_SECOND;
+    const SURVIVAL_SOONER: u32 = GravityCurve::FRAMES_PER_SECOND / 2;
+    const SURVIVAL_LAST: u32 = GravityCurve::FRAMES_PER_SECOND;
+    const SURVIVAL_CHANGE: u32 = 30;
+
+    // e.g. sprint-40, ultra-120, dig-10 or dig-10-30 when messy, the high scores of every
+    // goal are kept apart.
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Self::Marathon => "marathon".to_string(),
+            Self::Sprint { lines } => format!("sprint-{}", lines),
+            Self::Ultra { seconds } => format!("ultra-{}", seconds),
+            Self::Dig { rows, change: 0 } => format!("dig-{}", rows),
+            Self::Dig { rows, change } => format!("dig-{}-{}", rows, change),
+            Self::Survival => "survival".to_string(),
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "marathon" => Some(Self::Marathon),
+            "survival" => Some(Self::Survival),
+            "sprint" => Some(Self::Sprint {
+                lines: Self::DEFAULT_SPRINT_LINES,
+            }),
+            "ultra" => Some(Self::Ultra {
+                seconds: Self::DEFAULT_ULTRA_SECONDS,
+            }),
+            "dig" => Some(Self::Dig {
+                rows: Self::DEFAULT_DIG_ROWS,
+                change: 0,
+            }),
+            _ => {
+                if let Some(dig) = name.strip_prefix("dig-") {
+                    let (rows, change) = dig.split_once('-').unwrap_or((dig, "0"));
+                    let (rows, change) = (rows.parse().ok()?, change.parse().ok()?);
+                    return (Self::DIG_ROWS.contains(&rows) && change <= 100)
+                        .then_some(Self::Dig { rows, change });
+                }
+                if let Some(seconds) = name.strip_prefix("ultra-") {
+                    let seconds = seconds.parse().ok()?;
+                    return Self::ULTRA_SECONDS
+                        .contains(&seconds)
+                        .then_some(Self::Ultra { seconds });
+                }
+                let lines = name.strip_prefix("sprint-")?.parse().ok()?;
+                Self::SPRINT_LINES
+                    .contains(&lines)
+                    .then_some(Self::Sprint { lines })
+            }
+        }
+    }
+    // the board the game starts with.
+    pub(crate) fn setup(&self, engine: &mut Engine) {
+        if let Self::Dig { rows, change } = self {
+            let holes = engine.garbage_holes(*rows, *change);
+            engine.insert_garbage(&holes);
+        }
+    }
+    // a row of garbage rises at the end of some frames of a survival. false when the
+    // stack went over the top.
+    pub(crate) fn rise(&self, engine: &mut Engine, frames: u32) -> bool {
+        if *self != Self::Survival || !Self::rises_at(frames) {
+            return true;
+        }
+        let holes = engine.garbage_holes(1, Self::SURVIVAL_CHANGE);
+        engine.insert_garbage(&holes)
+    }
+    fn rises_at(frames: u32) -> bool {
+        let (mut at, mut next) = (0, Self::SURVIVAL_FIRST);
+        while at < frames {
+            at += next;
+            next = next
+                .saturating_sub(Self::SURVIVAL_SOONER)
+                .max(Self::SURVIVAL_LAST);
+        }
+        frames > 0 && at == frames
+    }
+    // the frames a game lasts at most, the clock counts down to the end of them.
+    pub(crate) fn time_limit(&self) -> Option<u32> {
+        match self {
+            Self::Ultra { seconds } => Some(seconds * GravityCurve::FRAMES_PER_SECOND),
+            _ => None,
+        }
+    }
+    // the goal is reached, or the time is up, after that many frames.
+    pub(crate) fn finished(&self, engine: &Engine, frames: u32) -> bool {
+        match self {
+            Self::Marathon => false,
+            Self::Sprint { lines } => engine.lines() >= *lines,
+            Self::Ultra { .. } => self.time_limit().is_some_and(|limit| frames >= limit),
+            Self::Dig { .. } => engine.garbage_rows() == 0,
+            Self::Survival => false,
+        }
+    }
+    // what the board tells when the game is finished.
+    pub(crate) fn finish_message(&self) -> &'static str {
+        match self {
+            Self::Ultra { .. } => "Time's up!",
+            _ => "Finished!",
+        }
+    }
+    // the races are won by time, not by points.
+    pub(crate) fn is_race(&self) -> bool {
+        matches!(self, Self::Sprint { .. } | Self::Dig { .. })
+    }
+    // a race which boomed before its goal has no place in the high scores, the points
+    // scored until then still count.
+    pub(crate) fn counts_boomed(&self) -> bool {
+        !self.is_race()
+    }
+    // the first is the better game: a higher score, a faster race or a longer survival.
+    pub(crate) fn better(&self, a: &Entry, b: &Entry) -> bool {
+        match self {
+            Self::Survival => a.frames > b.frames,
+            _ if self.is_race() => a.frames < b.frames,
+            _ => a.score > b.score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for lines in Mode::SPRINT_LINES {
+            let mode = Mode::Sprint { lines };
+            assert_eq!(Mode::by_name(&mode.name()), Some(mode));
+        }
+        assert_eq!(Mode::by_name("sprint"), Some(Mode::Sprint { lines: 40 }));
+        assert_eq!(Mode::by_name("marathon"), Some(Mode::Marathon));
+        assert_eq!(Mode::by_name("sprint-7"), None);
+        let ultra = Mode::Ultra { seconds: 180 };
+        assert_eq!(Mode::by_name(&ultra.name()), Some(ultra));
+        assert_eq!(Mode::by_name("ultra"), Some(Mode::Ultra { seconds: 120 }));
+        assert_eq!(Mode::by_name("ultra-0"), None);
+        for dig in [
+            Mode::Dig {
+                rows: 10,
+                change: 0,
+            },
+            Mode::Dig {
+                rows: 5,
+                change: 30,
+            },
+        ] {
+            assert_eq!(Mode::by_name(&dig.name()), Some(dig));
+        }
+        assert_eq!(Mode::by_name("dig-10-101"), None);
+        assert_eq!(Mode::by_name("survival"), Some(Mode::Survival));
+    }
+
+    #[test]
+    fn time_is_up() {
+        let engine = Engine::new(1, Default::default());
+        let ultra = Mode::Ultra { seconds: 120 };
+        assert!(!ultra.finished(&engine, 120 * 60 - 1));
+        assert!(ultra.finished(&engine, 120 * 60));
+        assert!(!Mode::Marathon.finished(&engine, u32::MAX));
+    }
+
+    #[test]
+    fn garbage_rises_sooner() {
+        let rises: Vec<u32> = (0..2000).filter(|f| Mode::rises_at(*f)).collect();
+        assert_eq!(rises, [600, 1170, 1710]);
+    }
+
+    #[test]
+    fn dig_through_clean_garbage() {
+        let mut engine = Engine::new(5, Default::default());
+        let dig = Mode::Dig { rows: 4, change: 0 };
+        dig.setup(&mut engine);
+        assert_eq!(engine.garbage_rows(), 4);
+        assert!(!dig.finished(&engine, 0));
+        // the holes are all in one column.
+        assert!(engine.rows()[1..=4].iter().all(|r| *r == engine.rows()[1]));
+        assert_eq!(engine.rows()[1].count_zeros(), 1);
+    }
+}
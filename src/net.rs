@@ -0,0 +1,580 @@
+// -- net.rs --
+
+use crate::{
+    codec::{Reader, Writer},
+    engine::Engine,
+    ruleset::Ruleset,
+};
+use std::{
+    convert::TryInto,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+// --
+
+/*
+    a message on the wire, all numbers little endian:
+
+    length:u32 tag:u8 fields
+
+    hello:   "TNET" version:u8 name:str
+    start:   seed:u64 ruleset
+    board:   length:varint engine
+    attack:  lines:i32
+    top out, bye: nothing
+*/
+
+// what the two sides of a game over the network tell each other. the host starts every
+// round, the blocks of both come from its seed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Message {
+    // the first message of each side, the versions have to be the same.
+    Hello { name: String },
+    Start { seed: u64, ruleset: Ruleset },
+    // the whole engine of the side, to be drawn on the other one.
+    Board { engine: Vec<u8> },
+    // garbage on its way, after what the attack cancelled.
+    Attack { lines: i32 },
+    TopOut,
+    Bye,
+}
+
+impl Message {
+    const MAGIC: &'static [u8; 4] = b"TNET";
+    const VERSION: u8 = 1;
+    // a board is a few hundred bytes, anything much bigger is not a message of ours.
+    const MAX_LENGTH: usize = 64 * 1024;
+    // more than that would fill the board anyway.
+    const MAX_ATTACK: i32 = Engine::ROWS_COUNT;
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        match self {
+            Self::Hello { name } => {
+                w.u8(0);
+                w.bytes(Self::MAGIC);
+                w.u8(Self::VERSION);
+                w.str(name);
+            }
+            Self::Start { seed, ruleset } => {
+                w.u8(1);
+                w.u64(*seed);
+                ruleset.encode(&mut w);
+            }
+            Self::Board { engine } => {
+                w.u8(2);
+                w.varint(engine.len() as u64);
+                w.bytes(engine);
+            }
+            Self::Attack { lines } => {
+                w.u8(3);
+                w.i32(*lines);
+            }
+            Self::TopOut => w.u8(4),
+            Self::Bye => w.u8(5),
+        }
+        let body = w.into_bytes();
+        let mut w = Writer::default();
+        w.u32(body.len() as u32);
+        w.bytes(&body);
+        w.into_bytes()
+    }
+    // the body of a message, without its length.
+    pub(crate) fn decode(body: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(body);
+        let message = match r.u8()? {
+            0 => {
+                if r.bytes(Self::MAGIC.len())? != Self::MAGIC {
+                    return Err("not a tetris game".to_string());
+                }
+                match r.u8()? {
+                    Self::VERSION => {}
+                    v => return Err(format!("unknown protocol version {}", v)),
+                }
+                Self::Hello { name: r.str()? }
+            }
+            1 => Self::Start {
+                seed: r.u64()?,
+                ruleset: Ruleset::decode(&mut r)?,
+            },
+            2 => {
+                let len = r.varint()? as usize;
+                Self::Board {
+                    engine: r.bytes(len)?.to_vec(),
+                }
+            }
+            3 => match r.i32()? {
+                lines if (0..=Self::MAX_ATTACK).contains(&lines) => Self::Attack { lines },
+                lines => return Err(format!("an attack of {} lines", lines)),
+            },
+            4 => Self::TopOut,
+            5 => Self::Bye,
+            t => return Err(format!("unknown message {}", t)),
+        };
+        if !r.is_empty() {
+            return Err("trailing bytes after the message".to_string());
+        }
+        Ok(message)
+    }
+}
+
+// --
+
+// a socket which never blocks the game: reads take what has come, writes what can go and
+// keep the rest for the next flush.
+pub(crate) struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    unsent: Vec<u8>,
+}
+
+impl Connection {
+    pub(crate) const DEFAULT_PORT: u16 = 7373;
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+    // that much left unsent, the other side has stopped reading.
+    const MAX_UNSENT: usize = 1024 * 1024;
+
+    pub(crate) fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            unsent: Vec::new(),
+        })
+    }
+    // e.g. localhost:7373, or only the host with the default port.
+    pub(crate) fn connect(address: &str) -> io::Result<Self> {
+        let address = match address.contains(':') {
+            true => address.to_string(),
+            false => format!("{}:{}", address, Self::DEFAULT_PORT),
+        };
+        let mut last = io::Error::new(io::ErrorKind::NotFound, "no address");
+        for a in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&a, Self::CONNECT_TIMEOUT) {
+                Ok(stream) => return Self::new(stream),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+    pub(crate) fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.unsent.extend(message.encode());
+        self.flush()
+    }
+    // what was not sent yet goes on as far as it can, e.g. once every frame.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        let mut sent = 0;
+        while sent < self.unsent.len() {
+            match self.stream.write(&self.unsent[sent..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => sent += n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.unsent.drain(..sent);
+        if self.unsent.len() > Self::MAX_UNSENT {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the other side stopped reading",
+            ));
+        }
+        Ok(())
+    }
+    // the messages which have come in full so far, an error once the other side is gone.
+    pub(crate) fn receive(&mut self) -> io::Result<Vec<Message>> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let mut messages = Vec::new();
+        while self.buffer.len() >= 4 {
+            let len = u32::from_le_bytes(self.buffer[..4].try_into().unwrap()) as usize;
+            if len > Message::MAX_LENGTH {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "message too long",
+                ));
+            }
+            if self.buffer.len() < 4 + len {
+                break;
+            }
+            let message = Message::decode(&self.buffer[4..4 + len])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.buffer.drain(..4 + len);
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
+
+// waits for the other side to join, without blocking the game.
+pub(crate) struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub(crate) fn listen(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener })
+    }
+    pub(crate) fn address(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+    pub(crate) fn accept(&self) -> io::Result<Option<Connection>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Connection::new(stream).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// --
+
+/*
+    an announcement, in one datagram:
+
+    "TLAN" version:u8 id:u64 port:u16 players:u8 name:str mode:str ruleset:str
+*/
+
+// a hosted game, as the host tells everyone on the network every second.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Announcement {
+    // the same for the whole time the game is hosted, it tells one heard twice apart.
+    pub(crate) id: u64,
+    // where the game waits for the other side, on the address it was heard from.
+    pub(crate) port: u16,
+    pub(crate) players: u8,
+    pub(crate) name: String,
+    pub(crate) mode: String,
+    pub(crate) ruleset: String,
+}
+
+impl Announcement {
+    const MAGIC: &'static [u8; 4] = b"TLAN";
+    const VERSION: u8 = 1;
+    pub(crate) const MAX_PLAYERS: u8 = 2;
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.bytes(Self::MAGIC);
+        w.u8(Self::VERSION);
+        w.u64(self.id);
+        w.u16(self.port);
+        w.u8(self.players);
+        w.str(&self.name);
+        w.str(&self.mode);
+        w.str(&self.ruleset);
+        w.into_bytes()
+    }
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(Self::MAGIC.len())? != Self::MAGIC {
+            return Err("not a tetris announcement".to_string());
+        }
+        match r.u8()? {
+            Self::VERSION => {}
+            v => return Err(format!("unknown announcement version {}", v)),
+        }
+        Ok(Self {
+            id: r.u64()?,
+            port: r.u16()?,
+            players: r.u8()?,
+            name: r.str()?,
+            mode: r.str()?,
+            ruleset: r.str()?,
+        })
+    }
+}
+
+// tells the network about a hosted game: a broadcast for the other machines, and the same
+// to this one, where a broadcast may not come back.
+pub(crate) struct Announcer {
+    socket: UdpSocket,
+    targets: [SocketAddr; 2],
+    last: Option<Instant>,
+}
+
+impl Announcer {
+    pub(crate) const DISCOVERY_PORT: u16 = Connection::DEFAULT_PORT + 1;
+    const EVERY: Duration = Duration::from_secs(1);
+
+    pub(crate) fn new(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            targets: [
+                (Ipv4Addr::BROADCAST, port).into(),
+                (Ipv4Addr::LOCALHOST, port).into(),
+            ],
+            last: None,
+        })
+    }
+    // at most once a second however often it is called, e.g. every frame. a network
+    // without a broadcast still hears of the game on this machine.
+    pub(crate) fn announce(&mut self, announcement: &Announcement) {
+        if self.last.is_some_and(|last| last.elapsed() < Self::EVERY) {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let bytes = announcement.encode();
+        for target in self.targets.iter() {
+            let _ = self.socket.send_to(&bytes, target);
+        }
+    }
+    // the next announce goes out right away, e.g. when a player joined.
+    pub(crate) fn changed(&mut self) {
+        self.last = None;
+    }
+}
+
+// a game heard of, at the address to join it.
+#[derive(Clone, Debug)]
+pub(crate) struct Game {
+    pub(crate) address: SocketAddr,
+    pub(crate) announcement: Announcement,
+    heard: Instant,
+}
+
+// the hosted games on the network, as long as they keep telling.
+pub(crate) struct Lobby {
+    socket: UdpSocket,
+    games: Vec<Game>,
+}
+
+impl Lobby {
+    // a host gone quiet for that long is gone.
+    const TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub(crate) fn listen(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            games: Vec::new(),
+        })
+    }
+    pub(crate) fn port(&self) -> io::Result<u16> {
+        self.socket.local_addr().map(|a| a.port())
+    }
+    pub(crate) fn games(&self) -> &[Game] {
+        &self.games
+    }
+    // takes in what was heard since, true when the list changed.
+    pub(crate) fn poll(&mut self) -> bool {
+        let mut changed = false;
+        let mut buffer = [0; 1024];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // e.g. a port unreachable of an earlier datagram on some systems, the rest
+                // is read on the next poll.
+                Err(e) => {
+                    eprintln!("lobby: {}", e);
+                    break;
+                }
+            };
+            let announcement = match Announcement::decode(&buffer[..len]) {
+                Ok(announcement) => announcement,
+                Err(_) => continue,
+            };
+            // the same game comes by broadcast and by loopback, the first address stays.
+            match self
+                .games
+                .iter_mut()
+                .find(|g| g.announcement.id == announcement.id)
+            {
+                Some(game) => {
+                    changed |= game.announcement != announcement;
+                    game.announcement = announcement;
+                    game.heard = Instant::now();
+                }
+                None => {
+                    self.games.push(Game {
+                        address: SocketAddr::new(from.ip(), announcement.port),
+                        announcement,
+                        heard: Instant::now(),
+                    });
+                    changed = true;
+                }
+            }
+        }
+        let count = self.games.len();
+        self.games.retain(|g| g.heard.elapsed() < Self::TIMEOUT);
+        changed || self.games.len() != count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn round_trip() {
+        let messages = [
+            Message::Hello {
+                name: "a".to_string(),
+            },
+            Message::Start {
+                seed: 42,
+                ruleset: Ruleset::guideline(),
+            },
+            Message::Board {
+                engine: vec![1, 2, 3],
+            },
+            Message::Attack { lines: 4 },
+            Message::TopOut,
+            Message::Bye,
+        ];
+        for m in messages {
+            let bytes = m.encode();
+            assert_eq!(
+                bytes.len(),
+                4 + u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize
+            );
+            assert_eq!(Message::decode(&bytes[4..]), Ok(m));
+        }
+        let mut hello = Message::Hello {
+            name: String::new(),
+        }
+        .encode();
+        hello[9] = Message::VERSION + 1;
+        assert!(Message::decode(&hello[4..]).is_err());
+        for lines in [-1, i32::MAX] {
+            assert!(Message::decode(&Message::Attack { lines }.encode()[4..]).is_err());
+        }
+    }
+
+    // a peer which never reads fills the buffers, the sends give up instead of waiting.
+    #[test]
+    fn stalled_peer() {
+        let host = Host::listen(0).unwrap();
+        let port = host.address().unwrap().port();
+        let _peer = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let started = Instant::now();
+        let mut conn = loop {
+            if let Some(conn) = host.accept().unwrap() {
+                break conn;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        let board = Message::Board {
+            engine: vec![0; 60 * 1024],
+        };
+        let mut sends = 0;
+        while conn.send(&board).is_ok() {
+            sends += 1;
+            assert!(sends < 1000);
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    // a stand-in for the other side, which says hello and sends garbage in two pieces.
+    #[test]
+    fn scripted_peer() {
+        let host = Host::listen(0).unwrap();
+        let port = host.address().unwrap().port();
+        let peer = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let mut bytes = Message::Hello {
+                name: "peer".to_string(),
+            }
+            .encode();
+            bytes.extend(Message::Attack { lines: 2 }.encode());
+            let (a, b) = bytes.split_at(6);
+            stream.write_all(a).unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            stream.write_all(b).unwrap();
+            let mut reply = [0; 5];
+            stream.read_exact(&mut reply).unwrap();
+            reply
+        });
+
+        let started = Instant::now();
+        let mut conn = loop {
+            if let Some(conn) = host.accept().unwrap() {
+                break conn;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            received.extend(conn.receive().unwrap());
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(
+            received,
+            [
+                Message::Hello {
+                    name: "peer".to_string()
+                },
+                Message::Attack { lines: 2 }
+            ]
+        );
+        conn.send(&Message::TopOut).unwrap();
+        assert_eq!(peer.join().unwrap().to_vec(), Message::TopOut.encode());
+    }
+
+    // a host and a lobby on this machine, over loopback.
+    #[test]
+    fn discovery() {
+        let mut lobby = Lobby::listen(0).unwrap();
+        let mut announcer = Announcer::new(lobby.port().unwrap()).unwrap();
+        let mut announcement = Announcement {
+            id: 9,
+            port: 7000,
+            players: 1,
+            name: "host".to_string(),
+            mode: "versus".to_string(),
+            ruleset: "guideline".to_string(),
+        };
+        assert_eq!(
+            Announcement::decode(&announcement.encode()),
+            Ok(announcement.clone())
+        );
+
+        let started = Instant::now();
+        announcer.announce(&announcement);
+        while lobby.games().is_empty() {
+            lobby.poll();
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let game = &lobby.games()[0];
+        assert_eq!(game.address.port(), 7000);
+        assert_eq!(game.announcement, announcement);
+
+        // once a second, unless something changed.
+        announcement.players = 2;
+        announcer.announce(&announcement);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!lobby.poll());
+        announcer.changed();
+        announcer.announce(&announcement);
+        while lobby.games()[0].announcement.players != 2 {
+            lobby.poll();
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(lobby.games().len(), 1);
+    }
+}
@@ -0,0 +1,454 @@
+// -- online.rs --
+
+use crate::{
+    codec::{Reader, Writer},
+    engine::{Engine, Status},
+    input::{Action, Bindings},
+    net::{Announcement, Announcer, Connection, Host, Message},
+    options::Options,
+    rng::GameRng,
+    ruleset::Ruleset,
+    stage::Stage,
+    versus::{Player, VersusWindow},
+    window::{with, FrameTimer},
+};
+use fltk::{
+    enums::{Align, Event, Shortcut},
+    group::Pack,
+    menu::{MenuBar, MenuFlag},
+    prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt, WindowExt},
+    window::DoubleWindow,
+};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+// --
+
+// how a round ended for this side.
+enum Outcome {
+    Won,
+    Lost,
+    // the other side is gone, and why.
+    Left(String),
+}
+
+// --
+
+// a versus against another instance over the network. the host waits for the other side
+// to join and starts every round with a seed of its own, then both sides play their own
+// board and send it over, with the garbage they attack with.
+pub(crate) struct OnlineWindow {
+    wind: DoubleWindow,
+    player: Player,
+    // the board of the other side, as it was sent last.
+    opponent: Rc<RefCell<Engine>>,
+    opponent_stage: Stage,
+
+    // only the host listens, for another side once one is gone too.
+    host: Option<Host>,
+    // and tells the lobbies on the network about the game.
+    announcer: Option<Announcer>,
+    id: u64,
+    connection: Option<Connection>,
+    // why the connection was lost while sending, told at the end of the tick.
+    left: Option<String>,
+    ruleset: Ruleset,
+    seed: u64,
+    // ours and theirs.
+    wins: [u32; 2],
+    playing: bool,
+
+    frames: u32,
+    timer: FrameTimer,
+    me: Weak<RefCell<Self>>,
+}
+
+impl OnlineWindow {
+    // the same as a versus, the board of this side is laid out by it.
+    const MENU_HEIGHT: i32 = VersusWindow::MENU_HEIGHT;
+    // the board is sent on every key and lock, and that often for the gravity.
+    const BOARD_FRAMES: u32 = 10;
+
+    fn build(options: &Options, me: Weak<RefCell<Self>>) -> Self {
+        let seed = options.seed.unwrap_or_else(GameRng::random_seed);
+        let ruleset = options.ruleset.clone();
+        let mut wind = DoubleWindow::default()
+            .with_size(620, 600 + Self::MENU_HEIGHT)
+            .center_screen();
+        let opponent = Rc::new(RefCell::new(Engine::new(seed, ruleset.clone())));
+        let mut menu = MenuBar::new(0, 0, 620, Self::MENU_HEIGHT, "");
+        let bindings = Bindings::load(&options.keys).unwrap_or_else(|e| {
+            eprintln!("{}: {}", options.keys.display(), e);
+            Bindings::default()
+        });
+        let engine = Engine::new(seed, ruleset.clone());
+        let mut player = Player::new(160, 10, engine, bindings, options.handling);
+        let mut pack = Pack::new(471, 40 + Self::MENU_HEIGHT, 150, 600, "");
+        let mut opponent_stage = Stage::new(1, 1, 1, 300, opponent.clone());
+        opponent_stage.set_label("Opponent:");
+        opponent_stage.set_align(Align::Top | Align::Left);
+        opponent_stage.set_label_size(24);
+        pack.end();
+        pack.set_spacing(30);
+        wind.end();
+
+        let m = me.clone();
+        menu.add(
+            "&Game/&Next round",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                with(&m, |t| t.new_round());
+            },
+        );
+        let m = me.clone();
+        menu.add("&Game/&Quit", Shortcut::None, MenuFlag::Normal, move |_| {
+            with(&m, |t| t.wind.hide());
+        });
+        let m = me.clone();
+        player
+            .stage
+            .handle(move |_, ev| with(&m, |t| t.handle(ev)).unwrap_or(false));
+        wind.show();
+
+        let mut t = Self {
+            wind,
+            player,
+            opponent,
+            opponent_stage,
+            host: None,
+            announcer: None,
+            id: GameRng::random_seed(),
+            connection: None,
+            left: None,
+            ruleset,
+            seed,
+            wins: [0; 2],
+            playing: false,
+            frames: 0,
+            timer: FrameTimer::new(false),
+            me,
+        };
+        t.connect(options);
+        t
+    }
+    pub(crate) fn new(options: &Options) -> Rc<RefCell<Self>> {
+        let online = Rc::new_cyclic(|me| RefCell::new(Self::build(options, me.clone())));
+        {
+            let mut t = online.borrow_mut();
+            t.relabel();
+            t.refresh();
+            t.start();
+        }
+        online
+    }
+    // listens with --host, or says hello to the host with --join.
+    fn connect(&mut self, options: &Options) {
+        let waiting = if let Some(port) = options.host {
+            match Host::listen(port) {
+                Ok(host) => {
+                    self.host = Some(host);
+                    match Announcer::new(Announcer::DISCOVERY_PORT) {
+                        Ok(announcer) => self.announcer = Some(announcer),
+                        Err(e) => eprintln!("announcing: {}", e),
+                    }
+                    "Waiting for a player"
+                }
+                Err(e) => {
+                    eprintln!("port {}: {}", port, e);
+                    "Cannot host"
+                }
+            }
+        } else if let Some(address) = options.join.as_deref() {
+            match Connection::connect(address) {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    self.send(&Self::hello());
+                    "Waiting for the host"
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", address, e);
+                    "Cannot join"
+                }
+            }
+        } else {
+            "Not connected"
+        };
+        self.player.stage.set_message(Some(waiting.to_string()));
+    }
+    fn player_name() -> String {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default()
+    }
+    fn hello() -> Message {
+        Message::Hello {
+            name: Self::player_name(),
+        }
+    }
+    // the hosted game, with how many play it.
+    fn announce(&mut self) {
+        let (announcer, host) = match (self.announcer.as_mut(), self.host.as_ref()) {
+            (Some(announcer), Some(host)) => (announcer, host),
+            _ => return,
+        };
+        let port = match host.address() {
+            Ok(address) => address.port(),
+            Err(_) => return,
+        };
+        announcer.announce(&Announcement {
+            id: self.id,
+            port,
+            players: 1 + self.connection.is_some() as u8,
+            name: Self::player_name(),
+            mode: "versus".to_string(),
+            ruleset: self.ruleset.key(),
+        });
+    }
+    // a failed send is told like the other side leaving.
+    fn send(&mut self, message: &Message) {
+        if let Some(connection) = self.connection.as_mut() {
+            if let Err(e) = connection.send(message) {
+                self.connection = None;
+                self.left = Some(e.to_string());
+            }
+        }
+    }
+    fn send_board(&mut self) {
+        let mut w = Writer::default();
+        self.player.engine.borrow().encode(&mut w);
+        self.send(&Message::Board {
+            engine: w.into_bytes(),
+        });
+    }
+    // the timer calls tick() every frame, the network is read even between rounds.
+    fn start(&mut self) {
+        let me = self.me.clone();
+        self.timer.start(move || match with(&me, |t| t.tick()) {
+            Some(Some(outcome)) => {
+                Self::round_over(&me, outcome);
+                false
+            }
+            Some(None) => true,
+            None => false,
+        });
+    }
+    fn relabel(&mut self) {
+        let side = match (&self.host, &self.connection) {
+            (Some(host), _) => match host.address() {
+                Ok(address) => format!("hosting on port {}", address.port()),
+                Err(_) => "hosting".to_string(),
+            },
+            (None, Some(_)) => "joined".to_string(),
+            (None, None) => "offline".to_string(),
+        };
+        let label = format!(
+            "Tetris - online - {} - {} : {} - seed: {}",
+            side, self.wins[0], self.wins[1], self.seed
+        );
+        self.wind.set_label(&label);
+    }
+    fn refresh(&mut self) {
+        self.player.refresh(self.wins[0]);
+        self.opponent_stage.redraw();
+    }
+    // the host sends the seed of a round, and both start it.
+    fn new_round(&mut self) {
+        if self.host.is_none() || self.connection.is_none() || self.playing {
+            return;
+        }
+        let (seed, ruleset) = (GameRng::random_seed(), self.ruleset.clone());
+        self.send(&Message::Start {
+            seed,
+            ruleset: ruleset.clone(),
+        });
+        self.start_round(seed, ruleset);
+    }
+    fn start_round(&mut self, seed: u64, ruleset: Ruleset) {
+        self.seed = seed;
+        *self.player.engine.borrow_mut() = Engine::new(seed, ruleset.clone());
+        *self.opponent.borrow_mut() = Engine::new(seed, ruleset.clone());
+        self.ruleset = ruleset;
+        self.player.new_round();
+        self.opponent_stage.set_message(None);
+        self.playing = true;
+        self.frames = 0;
+        self.send_board();
+        self.relabel();
+        self.refresh();
+    }
+    // Some when the round is over, or the other side left. the time stands still in
+    // between rounds.
+    fn tick(&mut self) -> Option<Outcome> {
+        let due = self.timer.due(if self.playing { 1.0 } else { 0.0 });
+        let had = self.connection.is_some();
+        let mut over = self.poll();
+        if let (Some(announcer), true) = (self.announcer.as_mut(), had != self.connection.is_some())
+        {
+            announcer.changed();
+        }
+        self.announce();
+        let mut frames = 0;
+        while self.playing && over.is_none() && frames < due {
+            frames += 1;
+            over = self.frame();
+        }
+        self.refresh();
+        over.or_else(|| self.left.take().map(Outcome::Left))
+    }
+    // takes in the other side, and what it sent.
+    fn poll(&mut self) -> Option<Outcome> {
+        if let (Some(host), None) = (&self.host, &self.connection) {
+            match host.accept() {
+                Ok(Some(connection)) => {
+                    self.connection = Some(connection);
+                    self.send(&Self::hello());
+                    self.relabel();
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        let connection = self.connection.as_mut()?;
+        let messages = match connection.flush().and_then(|_| connection.receive()) {
+            Ok(messages) => messages,
+            Err(e) => {
+                self.connection = None;
+                return Some(Outcome::Left(e.to_string()));
+            }
+        };
+        for message in messages {
+            match message {
+                // the version was checked on the way in.
+                Message::Hello { .. } if self.host.is_some() => self.new_round(),
+                Message::Hello { .. } => {}
+                Message::Start { seed, ruleset } if self.host.is_none() => {
+                    self.start_round(seed, ruleset)
+                }
+                Message::Start { .. } => {}
+                Message::Board { engine } => match Engine::decode(&mut Reader::new(&engine)) {
+                    Ok(engine) => *self.opponent.borrow_mut() = engine,
+                    Err(e) => eprintln!("opponent board: {}", e),
+                },
+                Message::Attack { lines } if self.playing => self.player.incoming.receive(lines),
+                Message::Attack { .. } => {}
+                Message::TopOut if self.playing => {
+                    self.playing = false;
+                    return Some(Outcome::Won);
+                }
+                Message::TopOut => {}
+                Message::Bye => {
+                    self.connection = None;
+                    return Some(Outcome::Left("the other side quit".to_string()));
+                }
+            }
+        }
+        None
+    }
+    fn frame(&mut self) -> Option<Outcome> {
+        self.frames += 1;
+        let keys = self.player.has_inputs();
+        let frame = self.player.frame();
+        let locked = matches!(frame, Some((Status::Freeze, _)));
+        let sent = frame.map(|(_, lines)| lines);
+        // the last board too, as it topped out.
+        if keys || locked || sent.is_none() || self.frames.is_multiple_of(Self::BOARD_FRAMES) {
+            self.send_board();
+        }
+        match sent {
+            None => {
+                self.send(&Message::TopOut);
+                self.playing = false;
+                return Some(Outcome::Lost);
+            }
+            Some(lines) if lines > 0 => self.send(&Message::Attack { lines }),
+            Some(_) => {}
+        }
+        None
+    }
+    // the host asks for the next round, the other side waits for it.
+    fn round_over(me: &Weak<RefCell<Self>>, outcome: Outcome) {
+        let hosting = with(me, |t| {
+            let (ours, theirs) = match outcome {
+                Outcome::Won => {
+                    t.wins[0] += 1;
+                    ("Winner!", "Boomed!!!")
+                }
+                Outcome::Lost => {
+                    t.wins[1] += 1;
+                    ("Boomed!!!", "Winner!")
+                }
+                Outcome::Left(_) => {
+                    t.playing = false;
+                    ("Left", "Left")
+                }
+            };
+            t.player.stage.set_message(Some(ours.to_string()));
+            t.opponent_stage.set_message(Some(theirs.to_string()));
+            t.relabel();
+            t.refresh();
+            t.host.is_some()
+        });
+        let hosting = match hosting {
+            Some(hosting) => hosting,
+            None => return,
+        };
+        match outcome {
+            Outcome::Left(why) => {
+                let text = match hosting {
+                    true => format!("The other side left: {}\n\nWaiting for another one.", why),
+                    false => format!("The host left: {}", why),
+                };
+                fltk::dialog::message_default(&text);
+            }
+            _ if hosting => {
+                let text = match outcome {
+                    Outcome::Won => "You win the round.",
+                    _ => "You lose the round.",
+                };
+                if fltk::dialog::choice2_default(text, "Quit", "Play again", "") != Some(1) {
+                    with(me, |t| t.wind.hide());
+                    return;
+                }
+                with(me, |t| t.new_round());
+            }
+            _ => {}
+        }
+        with(me, |t| t.start());
+    }
+    fn handle(&mut self, ev: Event) -> bool {
+        let key = fltk::app::event_key();
+        match ev {
+            Event::Focus => true,
+            // the game goes on, only the keys held are let go.
+            Event::Unfocus => {
+                self.player.release_all();
+                true
+            }
+            Event::KeyDown => match self.player.press(key) {
+                Some(Some(action)) => {
+                    match action {
+                        // the other side does not wait.
+                        Action::Pause | Action::Save => {}
+                        _ if self.playing => self.player.push(action),
+                        _ => {}
+                    }
+                    true
+                }
+                Some(None) => true,
+                None => false,
+            },
+            Event::KeyUp => self.player.release(key),
+            _ => false,
+        }
+    }
+}
+
+// the other side is told, instead of waiting for the connection to time out.
+impl Drop for OnlineWindow {
+    fn drop(&mut self) {
+        self.send(&Message::Bye);
+    }
+}
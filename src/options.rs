@@ -0,0 +1,244 @@
+// -- options.rs --
+
+use crate::{
+    config,
+    gravity::GravityCurve,
+    input::{Bindings, Handling},
+    mode::Mode,
+    net::Connection,
+    queue::Queue,
+    randomizer::RandomizerKind,
+    rotation::RotationKind,
+    ruleset::{LockDelay, Ruleset},
+    save::SavedGame,
+};
+use std::path::PathBuf;
+
+// --
+
+#[derive(Clone)]
+pub(crate) struct Options {
+    pub(crate) seed: Option<u64>,
+    pub(crate) ruleset: Ruleset,
+    pub(crate) mode: Mode,
+    pub(crate) previews: usize,
+    pub(crate) keys: PathBuf,
+    pub(crate) handling: Handling,
+    // a replay to watch instead of playing.
+    pub(crate) replay: Option<PathBuf>,
+    pub(crate) save: PathBuf,
+    // go on with the saved game, if there is one.
+    pub(crate) resume: bool,
+    // two players side by side, for the best of that many rounds.
+    pub(crate) versus: Option<u32>,
+    // a versus over the network, waiting on that port for the other side.
+    pub(crate) host: Option<u16>,
+    // or joining the one hosted at that address.
+    pub(crate) join: Option<String>,
+    // the games hosted on the network, to join one of them.
+    pub(crate) lobby: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            ruleset: Ruleset::default(),
+            mode: Mode::default(),
+            previews: 3,
+            keys: config::file(Bindings::FILE_NAME),
+            handling: Handling::default(),
+            replay: None,
+            save: config::file(SavedGame::FILE_NAME),
+            resume: true,
+            versus: None,
+            host: None,
+            join: None,
+            lobby: false,
+        }
+    }
+}
+
+impl Options {
+    const MAX_ROUNDS: u32 = 9;
+    // the options that make a game of their own, a saved one is not resumed with them.
+    const NEW_GAME: [&'static str; 11] = [
+        "--seed",
+        "--ruleset",
+        "--randomizer",
+        "--rotation",
+        "--lock-delay",
+        "--gravity",
+        "--mode",
+        "--lines",
+        "--seconds",
+        "--garbage-rows",
+        "--messiness",
+    ];
+
+    pub(crate) fn from_args() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+    pub(crate) fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut options = Self::default();
+        let mut lines = None;
+        let mut seconds = None;
+        let (mut garbage_rows, mut messiness) = (None, None);
+        while let Some(arg) = args.next() {
+            let (key, inline) = match arg.split_once('=') {
+                Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                None => (arg, None),
+            };
+            if Self::NEW_GAME.contains(&key.as_str()) {
+                options.resume = false;
+            }
+            let mut value = || inline.clone().or_else(|| args.next());
+            match key.as_str() {
+                "--seed" => match value().and_then(|v| v.parse().ok()) {
+                    Some(seed) => options.seed = Some(seed),
+                    None => eprintln!("--seed should be a number"),
+                },
+                "--previews" => match value().and_then(|v| v.parse().ok()) {
+                    Some(n) if (1..=Queue::MAX_PREVIEWS).contains(&n) => options.previews = n,
+                    _ => eprintln!("--previews should be 1 to {}", Queue::MAX_PREVIEWS),
+                },
+                "--keys" => match value() {
+                    Some(path) => options.keys = PathBuf::from(path),
+                    None => eprintln!("--keys should be followed by a file"),
+                },
+                "--replay" => match value() {
+                    Some(path) => options.replay = Some(PathBuf::from(path)),
+                    None => eprintln!("--replay should be followed by a file"),
+                },
+                "--save" => match value() {
+                    Some(path) => options.save = PathBuf::from(path),
+                    None => eprintln!("--save should be followed by a file"),
+                },
+                "--new" => options.resume = false,
+                "--das" => match value().and_then(|v| v.parse().ok()) {
+                    Some(ms) => options.handling.das_ms = ms,
+                    None => eprintln!("--das should be ms"),
+                },
+                "--arr" => match value().and_then(|v| v.parse().ok()) {
+                    Some(ms) => options.handling.arr_ms = ms,
+                    None => eprintln!("--arr should be ms"),
+                },
+                "--soft-drop" => match value().and_then(|v| v.parse().ok()) {
+                    Some(ms) => options.handling.soft_drop_ms = ms,
+                    None => eprintln!("--soft-drop should be ms"),
+                },
+                "--ruleset" => match value().and_then(|v| Ruleset::by_name(&v)) {
+                    Some(r) => options.ruleset = r,
+                    None => eprintln!("--ruleset should be one of {:?}", Ruleset::NAMES),
+                },
+                "--randomizer" => match value().and_then(|v| RandomizerKind::by_name(&v)) {
+                    Some(k) => options.ruleset.randomizer = k,
+                    None => eprintln!(
+                        "--randomizer should be one of {:?}",
+                        RandomizerKind::ALL
+                            .iter()
+                            .map(|k| k.name())
+                            .collect::<Vec<_>>()
+                    ),
+                },
+                "--rotation" => match value().and_then(|v| RotationKind::by_name(&v)) {
+                    Some(k) => options.ruleset.rotation = k,
+                    None => eprintln!(
+                        "--rotation should be one of {:?}",
+                        RotationKind::ALL
+                            .iter()
+                            .map(|k| k.name())
+                            .collect::<Vec<_>>()
+                    ),
+                },
+                "--lock-delay" => match value().as_deref() {
+                    Some("none") => options.ruleset.lock_delay = LockDelay::None,
+                    Some("infinity") => {
+                        options.ruleset.lock_delay = LockDelay::Infinity {
+                            ms: LockDelay::GUIDELINE_MS,
+                        }
+                    }
+                    Some(v) => match v.parse() {
+                        Ok(ms) => {
+                            options.ruleset.lock_delay = LockDelay::Limited {
+                                ms,
+                                resets: LockDelay::GUIDELINE_RESETS,
+                            }
+                        }
+                        Err(_) => eprintln!("--lock-delay should be none, infinity or ms"),
+                    },
+                    None => eprintln!("--lock-delay should be none, infinity or ms"),
+                },
+                "--gravity" => match value().and_then(|v| GravityCurve::by_name(&v)) {
+                    Some(g) => options.ruleset.gravity = g,
+                    None => eprintln!(
+                        "--gravity should be one of {:?}",
+                        GravityCurve::ALL
+                            .iter()
+                            .map(|g| g.name())
+                            .collect::<Vec<_>>()
+                    ),
+                },
+                "--mode" => match value().and_then(|v| Mode::by_name(&v)) {
+                    Some(m) => options.mode = m,
+                    None => eprintln!("--mode should be marathon, sprint, ultra, dig or survival"),
+                },
+                "--lines" => match value().and_then(|v| v.parse().ok()) {
+                    Some(n) if Mode::SPRINT_LINES.contains(&n) => lines = Some(n),
+                    _ => eprintln!("--lines should be one of {:?}", Mode::SPRINT_LINES),
+                },
+                "--seconds" => match value().and_then(|v| v.parse().ok()) {
+                    Some(n) if Mode::ULTRA_SECONDS.contains(&n) => seconds = Some(n),
+                    _ => eprintln!("--seconds should be {:?}", Mode::ULTRA_SECONDS),
+                },
+                "--garbage-rows" => match value().and_then(|v| v.parse().ok()) {
+                    Some(n) if Mode::DIG_ROWS.contains(&n) => garbage_rows = Some(n),
+                    _ => eprintln!("--garbage-rows should be {:?}", Mode::DIG_ROWS),
+                },
+                "--messiness" => match value().and_then(|v| v.parse().ok()) {
+                    Some(p) if p <= 100 => messiness = Some(p),
+                    _ => eprintln!("--messiness should be a percentage"),
+                },
+                "--versus" => match value().and_then(|v| v.parse().ok()) {
+                    Some(n) if n % 2 == 1 && n <= Self::MAX_ROUNDS => options.versus = Some(n),
+                    _ => eprintln!(
+                        "--versus should be an odd number of rounds up to {}",
+                        Self::MAX_ROUNDS
+                    ),
+                },
+                // only --host=port takes another port, the next argument is not one.
+                "--host" => match inline.as_deref().map(|v| v.parse()) {
+                    None => options.host = Some(Connection::DEFAULT_PORT),
+                    Some(Ok(port)) => options.host = Some(port),
+                    Some(Err(_)) => eprintln!("--host= should be followed by a port"),
+                },
+                "--join" => match value() {
+                    Some(address) => options.join = Some(address),
+                    None => eprintln!("--join should be followed by an address"),
+                },
+                "--lobby" => options.lobby = true,
+                _ => eprintln!("unknown option: {}", key),
+            }
+        }
+        // the lines of a sprint, which they make one.
+        if let Some(lines) = lines {
+            options.mode = Mode::Sprint { lines };
+        }
+        // and the time of an ultra.
+        if let Some(seconds) = seconds {
+            options.mode = Mode::Ultra { seconds };
+        }
+        // and the garbage of a dig.
+        if garbage_rows.is_some() || messiness.is_some() {
+            let (rows, change) = match options.mode {
+                Mode::Dig { rows, change } => (rows, change),
+                _ => (Mode::DEFAULT_DIG_ROWS, 0),
+            };
+            options.mode = Mode::Dig {
+                rows: garbage_rows.unwrap_or(rows),
+                change: messiness.unwrap_or(change),
+            };
+        }
+        options
+    }
+}
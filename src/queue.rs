@@ -0,0 +1,39 @@
+// -- queue.rs --
+
+use crate::blocks::Block;
+use std::collections::VecDeque;
+
+// --
+
+// the blocks coming next, always filled up to the longest preview so that how
+// many of them are shown never changes which blocks come.
+#[derive(Clone, Default)]
+pub(crate) struct Queue {
+    blocks: VecDeque<Block>,
+}
+
+impl Queue {
+    pub(crate) const MAX_PREVIEWS: usize = 6;
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.blocks.len() >= Self::MAX_PREVIEWS
+    }
+    pub(crate) fn push(&mut self, b: Block) {
+        self.blocks.push_back(b);
+    }
+    pub(crate) fn pop(&mut self) -> Option<Block> {
+        self.blocks.pop_front()
+    }
+    pub(crate) fn front(&self) -> Option<&Block> {
+        self.blocks.front()
+    }
+    pub(crate) fn clear(&mut self) {
+        self.blocks.clear();
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter()
+    }
+    pub(crate) fn previews(&self, count: usize) -> Vec<Block> {
+        self.blocks.iter().take(count).copied().collect()
+    }
+}
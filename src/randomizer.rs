@@ -0,0 +1,234 @@
+// -- randomizer.rs --
+
+use crate::{
+    blocks::Block,
+    codec::{Reader, Writer},
+    rng::GameRng,
+};
+use rand::{seq::SliceRandom, Rng};
+
+// --
+
+// decides which kind of block comes next, all the randomness comes from the game rng.
+pub(crate) trait Randomizer {
+    fn next(&mut self, rng: &mut GameRng) -> usize;
+    // what it remembers of the blocks dealt so far, for a saved game.
+    fn encode(&self, _w: &mut Writer) {}
+    fn decode(&mut self, _r: &mut Reader) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn read_kind(r: &mut Reader) -> Result<usize, String> {
+    match r.u8()? as usize {
+        k if k < Block::BLOCKS_COUNT => Ok(k),
+        k => Err(format!("unknown block {}", k)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RandomizerKind {
+    Random,
+    Bag7,
+    Bag14,
+    TgmHistory,
+    Nes,
+}
+
+impl RandomizerKind {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Random,
+        Self::Bag7,
+        Self::Bag14,
+        Self::TgmHistory,
+        Self::Nes,
+    ];
+
+    pub(crate) fn create(self) -> Box<dyn Randomizer> {
+        match self {
+            Self::Random => Box::new(PureRandom),
+            Self::Bag7 => Box::new(Bag::new(1)),
+            Self::Bag14 => Box::new(Bag::new(2)),
+            Self::TgmHistory => Box::new(TgmHistory::new()),
+            Self::Nes => Box::new(Nes::new()),
+        }
+    }
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Random => "random",
+            Self::Bag7 => "7-bag",
+            Self::Bag14 => "14-bag",
+            Self::TgmHistory => "tgm",
+            Self::Nes => "nes",
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.name() == name)
+    }
+}
+
+// --
+
+// every kind has the same chance, long droughts are possible.
+pub(crate) struct PureRandom;
+
+impl Randomizer for PureRandom {
+    fn next(&mut self, rng: &mut GameRng) -> usize {
+        rng.gen_range(0..Block::BLOCKS_COUNT)
+    }
+}
+
+// --
+
+// deals every kind `copies` times from a shuffled bag, then refills it.
+pub(crate) struct Bag {
+    copies: usize,
+    bag: Vec<usize>,
+}
+
+impl Bag {
+    pub(crate) fn new(copies: usize) -> Self {
+        Self {
+            copies,
+            bag: Vec::with_capacity(copies * Block::BLOCKS_COUNT),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self, rng: &mut GameRng) -> usize {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(0..Block::BLOCKS_COUNT);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap()
+    }
+    fn encode(&self, w: &mut Writer) {
+        w.varint(self.bag.len() as u64);
+        self.bag.iter().for_each(|k| w.u8(*k as u8));
+    }
+    fn decode(&mut self, r: &mut Reader) -> Result<(), String> {
+        let len = r.varint()? as usize;
+        if len > self.copies * Block::BLOCKS_COUNT {
+            return Err(format!("a bag of {} blocks", len));
+        }
+        self.bag = (0..len).map(|_| read_kind(r)).collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+// --
+
+// rerolls a few times when the kind is one of the last four dealt.
+pub(crate) struct TgmHistory {
+    history: [usize; Self::HISTORY],
+    first: bool,
+}
+
+impl TgmHistory {
+    const HISTORY: usize = 4;
+    const ROLLS: usize = 4;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            history: [Block::Z, Block::Z, Block::S, Block::S],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next(&mut self, rng: &mut GameRng) -> usize {
+        let mut kind = 0;
+        if self.first {
+            // never start with a piece that forces an overhang
+            self.first = false;
+            kind = [Block::I, Block::J, Block::L, Block::T][rng.gen_range(0..4)];
+        } else {
+            for _ in 0..Self::ROLLS {
+                kind = rng.gen_range(0..Block::BLOCKS_COUNT);
+                if !self.history.contains(&kind) {
+                    break;
+                }
+            }
+        }
+        self.history.rotate_right(1);
+        self.history[0] = kind;
+        kind
+    }
+    fn encode(&self, w: &mut Writer) {
+        self.history.iter().for_each(|k| w.u8(*k as u8));
+        w.bool(self.first);
+    }
+    fn decode(&mut self, r: &mut Reader) -> Result<(), String> {
+        for k in self.history.iter_mut() {
+            *k = read_kind(r)?;
+        }
+        self.first = r.bool()?;
+        Ok(())
+    }
+}
+
+// --
+
+// rolls one extra "reroll" value, and rerolls once on it or on a repeat.
+pub(crate) struct Nes {
+    last: Option<usize>,
+}
+
+impl Nes {
+    pub(crate) fn new() -> Self {
+        Self { last: None }
+    }
+}
+
+impl Randomizer for Nes {
+    fn next(&mut self, rng: &mut GameRng) -> usize {
+        let mut kind = rng.gen_range(0..=Block::BLOCKS_COUNT);
+        if kind == Block::BLOCKS_COUNT || Some(kind) == self.last {
+            kind = rng.gen_range(0..Block::BLOCKS_COUNT);
+        }
+        self.last = Some(kind);
+        kind
+    }
+    fn encode(&self, w: &mut Writer) {
+        w.u8(self.last.map_or(u8::MAX, |k| k as u8));
+    }
+    fn decode(&mut self, r: &mut Reader) -> Result<(), String> {
+        self.last = match r.u8()? {
+            u8::MAX => None,
+            k if (k as usize) < Block::BLOCKS_COUNT => Some(k as usize),
+            k => return Err(format!("unknown block {}", k)),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bag_deals_each_kind_once() {
+        let mut rng = GameRng::new(1);
+        let mut bag = RandomizerKind::Bag7.create();
+        for _ in 0..10 {
+            let mut kinds: Vec<usize> = (0..Block::BLOCKS_COUNT)
+                .map(|_| bag.next(&mut rng))
+                .collect();
+            kinds.sort_unstable();
+            assert_eq!(kinds, (0..Block::BLOCKS_COUNT).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn tgm_first_piece() {
+        for seed in 0..50 {
+            let mut rng = GameRng::new(seed);
+            let kind = TgmHistory::new().next(&mut rng);
+            assert!([Block::I, Block::J, Block::L, Block::T].contains(&kind));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// -- replay.rs --
+
+use crate::{
+    codec::{Reader, Writer},
+    config,
+    input::Action,
+    mode::Mode,
+    ruleset::Ruleset,
+};
+use std::{
+    convert::TryFrom,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// --
+
+/*
+    a replay file, all numbers little endian:
+
+    "TRPL" version:u8 seed:u64 ruleset mode:str frames:varint count:varint
+    count * (frames since the previous action:varint, action:u8)
+*/
+
+// a game as its seed, its rules and the actions of the player frame by frame,
+// the engine plays it again the same way.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Replay {
+    seed: u64,
+    ruleset: Ruleset,
+    mode: Mode,
+    actions: Vec<(u32, Action)>,
+    frames: u32,
+}
+
+impl Replay {
+    const MAGIC: &'static [u8; 4] = b"TRPL";
+    const VERSION: u8 = 1;
+    pub(crate) const EXTENSION: &'static str = "replay";
+
+    pub(crate) fn new(seed: u64, ruleset: Ruleset, mode: Mode) -> Self {
+        Self {
+            seed,
+            ruleset,
+            mode,
+            actions: Vec::new(),
+            frames: 0,
+        }
+    }
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub(crate) fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+    pub(crate) fn mode(&self) -> Mode {
+        self.mode
+    }
+    // how many frames the game lasted.
+    pub(crate) fn frames(&self) -> u32 {
+        self.frames
+    }
+    // the actions of a frame are applied before it runs, in the order they came.
+    pub(crate) fn push(&mut self, frame: u32, action: Action) {
+        self.actions.push((frame, action));
+        self.frames = self.frames.max(frame + 1);
+    }
+    pub(crate) fn set_frames(&mut self, frames: u32) {
+        self.frames = self.frames.max(frames);
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.bytes(Self::MAGIC);
+        w.u8(Self::VERSION);
+        w.u64(self.seed);
+        self.ruleset.encode(&mut w);
+        w.str(&self.mode.name());
+        w.varint(self.frames as u64);
+        w.varint(self.actions.len() as u64);
+        let mut last = 0;
+        for (frame, action) in self.actions.iter() {
+            w.varint((frame - last) as u64);
+            w.u8(action.index() as u8);
+            last = *frame;
+        }
+        w.into_bytes()
+    }
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(Self::MAGIC.len())? != Self::MAGIC {
+            return Err("not a replay".to_string());
+        }
+        match r.u8()? {
+            Self::VERSION => {}
+            v => return Err(format!("unknown replay version {}", v)),
+        }
+        let seed = r.u64()?;
+        let ruleset = Ruleset::decode(&mut r)?;
+        let name = r.str()?;
+        let mode = Mode::by_name(&name).ok_or_else(|| format!("unknown mode {}", name))?;
+        let too_long = || "the replay is too long".to_string();
+        let frames = u32::try_from(r.varint()?).map_err(|_| too_long())?;
+        let count = r.varint()? as usize;
+        let mut actions = Vec::with_capacity(count.min(bytes.len()));
+        let mut frame: u32 = 0;
+        for _ in 0..count {
+            frame = u32::try_from(r.varint()?)
+                .ok()
+                .and_then(|d| frame.checked_add(d))
+                .ok_or_else(too_long)?;
+            let i = r.u8()? as usize;
+            let action = *Action::ALL
+                .get(i)
+                .ok_or_else(|| format!("unknown action {}", i))?;
+            actions.push((frame, action));
+        }
+        if !r.is_empty() {
+            return Err("trailing bytes after the replay".to_string());
+        }
+        Ok(Self {
+            seed,
+            ruleset,
+            mode,
+            actions,
+            frames,
+        })
+    }
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        Self::decode(&fs::read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+    // where the games are recorded, one file each.
+    pub(crate) fn file(seed: u64, started: u64) -> PathBuf {
+        config::dir()
+            .join("replays")
+            .join(format!("{}-{}.{}", started, seed, Self::EXTENSION))
+    }
+}
+
+// --
+
+// plays a replay back, frame by frame.
+pub(crate) struct Player {
+    replay: Replay,
+    next: usize,
+    speed: usize,
+    pub(crate) playing: bool,
+}
+
+impl Player {
+    const SPEEDS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+    pub(crate) fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            speed: 2,
+            playing: true,
+        }
+    }
+    pub(crate) fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    // the actions of the frame, which must come right after the one before.
+    pub(crate) fn actions(&mut self, frame: u32) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Some((f, a)) = self.replay.actions.get(self.next) {
+            if *f > frame {
+                break;
+            }
+            if *f == frame {
+                actions.push(*a);
+            }
+            self.next += 1;
+        }
+        actions
+    }
+    pub(crate) fn rewind(&mut self) {
+        self.next = 0;
+    }
+    pub(crate) fn speed(&self) -> f64 {
+        Self::SPEEDS[self.speed]
+    }
+    pub(crate) fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(Self::SPEEDS.len() - 1);
+    }
+    pub(crate) fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    #[test]
+    fn round_trip() {
+        let mut r = Replay::new(42, Ruleset::guideline(), Mode::Sprint { lines: 40 });
+        r.push(0, Action::MoveLeft);
+        r.push(0, Action::RotateCW);
+        r.push(300, Action::HardDrop);
+        r.set_frames(400);
+        assert_eq!(Replay::decode(&r.encode()), Ok(r.clone()));
+        assert!(Replay::decode(&r.encode()[..10]).is_err());
+    }
+
+    #[test]
+    fn frames_past_the_end() {
+        let replay = |deltas: &[u64]| {
+            let mut w = Writer::default();
+            w.bytes(Replay::MAGIC);
+            w.u8(Replay::VERSION);
+            w.u64(42);
+            Ruleset::guideline().encode(&mut w);
+            w.str(&Mode::Marathon.name());
+            w.varint(0);
+            w.varint(deltas.len() as u64);
+            for d in deltas {
+                w.varint(*d);
+                w.u8(Action::HardDrop.index() as u8);
+            }
+            Replay::decode(&w.into_bytes())
+        };
+        assert!(replay(&[u32::MAX as u64]).is_ok());
+        assert!(replay(&[u32::MAX as u64, 1]).is_err());
+        assert!(replay(&[u32::MAX as u64 + 1]).is_err());
+    }
+
+    #[test]
+    fn plays_the_same_game() {
+        let moves = [
+            Action::MoveLeft,
+            Action::HardDrop,
+            Action::Hold,
+            Action::RotateCW,
+            Action::MoveRight,
+            Action::MoveRight,
+            Action::HardDrop,
+        ];
+        let mut engine = Engine::new(7, Ruleset::guideline());
+        let mut replay = Replay::new(7, Ruleset::guideline(), Mode::Marathon);
+        for frame in 0..600 {
+            let actions: Vec<Action> = (frame % 10 == 0)
+                .then(|| moves[frame as usize / 10 % moves.len()])
+                .into_iter()
+                .collect();
+            actions.iter().for_each(|a| replay.push(frame, *a));
+            engine.step(&actions);
+        }
+        replay.set_frames(600);
+
+        let replay = Replay::decode(&replay.encode()).unwrap();
+        let mut player = Player::new(replay.clone());
+        let mut again = Engine::new(replay.seed(), replay.ruleset().clone());
+        for frame in 0..replay.frames() {
+            again.step(&player.actions(frame));
+        }
+        assert_eq!(again.rows(), engine.rows());
+        assert_eq!(again.score(), engine.score());
+        assert!(engine.lines() > 0 || engine.score() > 0);
+    }
+}
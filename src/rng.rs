@@ -0,0 +1,74 @@
+// -- rng.rs --
+
+use rand::{Error, RngCore, SeedableRng};
+
+// --
+
+// splitmix64, small and fast, and the whole state is one u64 so a game
+// can be reproduced (or saved) from its seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub(crate) fn random_seed() -> u64 {
+        rand::random()
+    }
+    // a saved game goes on with GameRng::new(state).
+    pub(crate) fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let v = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&v[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u64::from_le_bytes(seed))
+    }
+    fn seed_from_u64(state: u64) -> Self {
+        Self::new(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameRng;
+    use rand::Rng;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.gen_range(0..7), b.gen_range(0..7));
+        }
+    }
+}
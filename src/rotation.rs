@@ -0,0 +1,267 @@
+// -- rotation.rs --
+
+use crate::{
+    blocks::{Block, Posture, Shapes},
+    rng::GameRng,
+};
+
+// --
+
+// how the blocks look in every posture, where they appear and how they are
+// pushed around when a rotation collides.
+pub(crate) trait RotationSystem {
+    fn shapes(&self) -> &'static Shapes;
+    fn spawn_posture(&self, rng: &mut GameRng) -> Posture;
+    fn spawn_col(&self) -> i32;
+    // offsets (right, up) tried in turn, the first one that fits wins.
+    fn kicks(&self, index: usize, from: Posture, to: Posture) -> &'static [(i32, i32)];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RotationKind {
+    Classic,
+    Srs,
+}
+
+impl RotationKind {
+    pub(crate) const ALL: [Self; 2] = [Self::Classic, Self::Srs];
+
+    pub(crate) fn create(self) -> Box<dyn RotationSystem> {
+        match self {
+            Self::Classic => Box::new(Classic),
+            Self::Srs => Box::new(Srs),
+        }
+    }
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Srs => "srs",
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|k| k.name() == name)
+    }
+}
+
+// --
+
+// random posture at spawn, and a rotation that collides is simply refused.
+pub(crate) struct Classic;
+
+impl Classic {
+    const SHAPES: Shapes = [
+        // I block
+        [
+            0b0100_0100_0100_0100,
+            0b0000_1111_0000_0000,
+            0b0100_0100_0100_0100,
+            0b0000_1111_0000_0000,
+        ],
+        // O block
+        [
+            0b0000_1100_1100_0000,
+            0b0000_1100_1100_0000,
+            0b0000_1100_1100_0000,
+            0b0000_1100_1100_0000,
+        ],
+        // Z block
+        [
+            0b0000_1100_0110_0000,
+            0b0010_0110_0100_0000,
+            0b0000_1100_0110_0000,
+            0b0010_0110_0100_0000,
+        ],
+        // S block
+        [
+            0b0000_0110_1100_0000,
+            0b1000_1100_0100_0000,
+            0b0000_0110_1100_0000,
+            0b1000_1100_0100_0000,
+        ],
+        // L block
+        [
+            0b0100_0100_0110_0000,
+            0b0000_1110_1000_0000,
+            0b1100_0100_0100_0000,
+            0b0010_1110_0000_0000,
+        ],
+        // J block
+        [
+            0b0100_0100_1100_0000,
+            0b1000_1110_0000_0000,
+            0b0110_0100_0100_0000,
+            0b0000_1110_0010_0000,
+        ],
+        // T block
+        [
+            0b0000_1110_0100_0000,
+            0b0100_1100_0100_0000,
+            0b0100_1110_0000_0000,
+            0b0100_0110_0100_0000,
+        ],
+    ];
+}
+
+impl RotationSystem for Classic {
+    fn shapes(&self) -> &'static Shapes {
+        &Self::SHAPES
+    }
+    fn spawn_posture(&self, rng: &mut GameRng) -> Posture {
+        Posture::new(rng)
+    }
+    fn spawn_col(&self) -> i32 {
+        7
+    }
+    fn kicks(&self, _: usize, _: Posture, _: Posture) -> &'static [(i32, i32)] {
+        &[(0, 0)]
+    }
+}
+
+// --
+
+// the super rotation system of the guideline, postures are 0, R, 2 and L.
+pub(crate) struct Srs;
+
+impl Srs {
+    const SHAPES: Shapes = [
+        // I block
+        [
+            0b0000_1111_0000_0000,
+            0b0010_0010_0010_0010,
+            0b0000_0000_1111_0000,
+            0b0100_0100_0100_0100,
+        ],
+        // O block
+        [
+            0b0110_0110_0000_0000,
+            0b0110_0110_0000_0000,
+            0b0110_0110_0000_0000,
+            0b0110_0110_0000_0000,
+        ],
+        // Z block
+        [
+            0b1100_0110_0000_0000,
+            0b0010_0110_0100_0000,
+            0b0000_1100_0110_0000,
+            0b0100_1100_1000_0000,
+        ],
+        // S block
+        [
+            0b0110_1100_0000_0000,
+            0b0100_0110_0010_0000,
+            0b0000_0110_1100_0000,
+            0b1000_1100_0100_0000,
+        ],
+        // L block
+        [
+            0b0010_1110_0000_0000,
+            0b0100_0100_0110_0000,
+            0b0000_1110_1000_0000,
+            0b1100_0100_0100_0000,
+        ],
+        // J block
+        [
+            0b1000_1110_0000_0000,
+            0b0110_0100_0100_0000,
+            0b0000_1110_0010_0000,
+            0b0100_0100_1100_0000,
+        ],
+        // T block
+        [
+            0b0100_1110_0000_0000,
+            0b0100_0110_0100_0000,
+            0b0000_1110_0100_0000,
+            0b0100_1100_0100_0000,
+        ],
+    ];
+    // indexed by the posture rotated from, clockwise then anticlockwise.
+    const JLSTZ_KICKS: [[[(i32, i32); 5]; 2]; 4] = [
+        [
+            [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+            [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 0 -> L
+        ],
+        [
+            [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)], // R -> 2
+            [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)], // R -> 0
+        ],
+        [
+            [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+            [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 2 -> R
+        ],
+        [
+            [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)], // L -> 0
+            [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)], // L -> 2
+        ],
+    ];
+    const I_KICKS: [[[(i32, i32); 5]; 2]; 4] = [
+        [
+            [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // 0 -> R
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // 0 -> L
+        ],
+        [
+            [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // R -> 2
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // R -> 0
+        ],
+        [
+            [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // 2 -> L
+            [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // 2 -> R
+        ],
+        [
+            [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // L -> 0
+            [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // L -> 2
+        ],
+    ];
+}
+
+impl RotationSystem for Srs {
+    fn shapes(&self) -> &'static Shapes {
+        &Self::SHAPES
+    }
+    fn spawn_posture(&self, _: &mut GameRng) -> Posture {
+        Posture::default()
+    }
+    fn spawn_col(&self) -> i32 {
+        6
+    }
+    fn kicks(&self, index: usize, from: Posture, to: Posture) -> &'static [(i32, i32)] {
+        let mut cw = from;
+        cw.clockwise();
+        let dir = if cw.index() == to.index() { 0 } else { 1 };
+        match index {
+            Block::O => &[(0, 0)],
+            Block::I => &Self::I_KICKS[from.index()][dir],
+            _ => &Self::JLSTZ_KICKS[from.index()][dir],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four_cells_in_every_shape() {
+        for system in RotationKind::ALL.iter() {
+            for shape in system.create().shapes().iter().flatten() {
+                assert_eq!(shape.count_ones(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn srs_kicks_undo_each_other() {
+        let srs = Srs;
+        for index in [Block::I, Block::T].iter() {
+            for from in 0..Posture::COUNT {
+                let from = Posture::from(from);
+                let mut to = from;
+                to.clockwise();
+                let there = srs.kicks(*index, from, to);
+                let back = srs.kicks(*index, to, from);
+                for (a, b) in there.iter().zip(back.iter()) {
+                    assert_eq!((a.0 + b.0, a.1 + b.1), (0, 0));
+                }
+            }
+        }
+    }
+}
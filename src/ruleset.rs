@@ -0,0 +1,173 @@
+// -- ruleset.rs --
+
+use crate::{
+    codec::{Reader, Writer},
+    gravity::GravityCurve,
+    randomizer::RandomizerKind,
+    rotation::RotationKind,
+};
+
+// --
+
+// how long a block may stay on the ground before it freezes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LockDelay {
+    // freezes as soon as gravity finds the ground under it.
+    None,
+    // every successful move or rotation restarts the delay, at most `resets` times
+    // until the block reaches a lower row.
+    Limited { ms: u32, resets: u32 },
+    Infinity { ms: u32 },
+}
+
+impl LockDelay {
+    pub(crate) const GUIDELINE_MS: u32 = 500;
+    pub(crate) const GUIDELINE_RESETS: u32 = 15;
+}
+
+// the knobs that differ between the flavours of the game.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Ruleset {
+    pub(crate) name: &'static str,
+    pub(crate) randomizer: RandomizerKind,
+    pub(crate) rotation: RotationKind,
+    pub(crate) lock_delay: LockDelay,
+    pub(crate) gravity: GravityCurve,
+}
+
+impl Ruleset {
+    pub(crate) const NAMES: [&'static str; 4] = ["classic", "guideline", "tgm", "nes"];
+
+    // the rules this game always had.
+    pub(crate) fn classic() -> Self {
+        Self {
+            name: "classic",
+            randomizer: RandomizerKind::Random,
+            rotation: RotationKind::Classic,
+            lock_delay: LockDelay::None,
+            gravity: GravityCurve::Classic,
+        }
+    }
+    pub(crate) fn guideline() -> Self {
+        Self {
+            name: "guideline",
+            randomizer: RandomizerKind::Bag7,
+            rotation: RotationKind::Srs,
+            lock_delay: LockDelay::Limited {
+                ms: LockDelay::GUIDELINE_MS,
+                resets: LockDelay::GUIDELINE_RESETS,
+            },
+            gravity: GravityCurve::Guideline,
+        }
+    }
+    pub(crate) fn tgm() -> Self {
+        Self {
+            name: "tgm",
+            randomizer: RandomizerKind::TgmHistory,
+            rotation: RotationKind::Classic,
+            lock_delay: LockDelay::None,
+            gravity: GravityCurve::Guideline,
+        }
+    }
+    pub(crate) fn nes() -> Self {
+        Self {
+            name: "nes",
+            randomizer: RandomizerKind::Nes,
+            rotation: RotationKind::Classic,
+            lock_delay: LockDelay::None,
+            gravity: GravityCurve::Classic,
+        }
+    }
+    pub(crate) fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "guideline" => Some(Self::guideline()),
+            "tgm" => Some(Self::tgm()),
+            "nes" => Some(Self::nes()),
+            _ => None,
+        }
+    }
+    // what the high scores are kept by: the name alone while the rules are as named, else
+    // every knob, e.g. guideline/7-bag/srs/guideline/lock 500x15, so that a game with
+    // easier rules never ranks among the named ones.
+    pub(crate) fn key(&self) -> String {
+        if Self::by_name(self.name).as_ref() == Some(self) {
+            return self.name.to_string();
+        }
+        let lock_delay = match self.lock_delay {
+            LockDelay::None => "lock none".to_string(),
+            LockDelay::Limited { ms, resets } => format!("lock {}x{}", ms, resets),
+            LockDelay::Infinity { ms } => format!("lock {} infinity", ms),
+        };
+        format!(
+            "{}/{}/{}/{}/{}",
+            self.name,
+            self.randomizer.name(),
+            self.rotation.name(),
+            self.gravity.name(),
+            lock_delay
+        )
+    }
+    // by names rather than numbers, so that the files outlive a reordering of the kinds.
+    pub(crate) fn encode(&self, w: &mut Writer) {
+        w.str(self.name);
+        w.str(self.randomizer.name());
+        w.str(self.rotation.name());
+        w.str(self.gravity.name());
+        let (tag, ms, resets) = match self.lock_delay {
+            LockDelay::None => (0, 0, 0),
+            LockDelay::Limited { ms, resets } => (1, ms, resets),
+            LockDelay::Infinity { ms } => (2, ms, 0),
+        };
+        w.u8(tag);
+        w.u32(ms);
+        w.u32(resets);
+    }
+    pub(crate) fn decode(r: &mut Reader) -> Result<Self, String> {
+        let name = r.str()?;
+        let mut ruleset =
+            Self::by_name(&name).ok_or_else(|| format!("unknown ruleset {}", name))?;
+        let name = r.str()?;
+        ruleset.randomizer =
+            RandomizerKind::by_name(&name).ok_or_else(|| format!("unknown randomizer {}", name))?;
+        let name = r.str()?;
+        ruleset.rotation =
+            RotationKind::by_name(&name).ok_or_else(|| format!("unknown rotation {}", name))?;
+        let name = r.str()?;
+        ruleset.gravity =
+            GravityCurve::by_name(&name).ok_or_else(|| format!("unknown gravity {}", name))?;
+        let (tag, ms, resets) = (r.u8()?, r.u32()?, r.u32()?);
+        ruleset.lock_delay = match tag {
+            0 => LockDelay::None,
+            1 => LockDelay::Limited { ms, resets },
+            2 => LockDelay::Infinity { ms },
+            _ => return Err(format!("unknown lock delay {}", tag)),
+        };
+        Ok(ruleset)
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overridden_rules_have_their_own_key() {
+        assert_eq!(Ruleset::guideline().key(), "guideline");
+        let mut ruleset = Ruleset::guideline();
+        ruleset.lock_delay = LockDelay::Infinity { ms: 500 };
+        assert_eq!(
+            ruleset.key(),
+            "guideline/7-bag/srs/guideline/lock 500 infinity"
+        );
+        ruleset.lock_delay = Ruleset::tgm().lock_delay;
+        ruleset.gravity = GravityCurve::Twenty;
+        assert_ne!(ruleset.key(), Ruleset::guideline().key());
+    }
+}
@@ -0,0 +1,137 @@
+// -- save.rs --
+
+use crate::{
+    codec::{Reader, Writer},
+    engine::Engine,
+    finesse::Finesse,
+    replay::Replay,
+};
+use std::{fs, io, path::Path};
+
+// --
+
+/*
+    a saved game, all numbers little endian:
+
+    "TSAV" version:u8 started:u64 engine finesse replay-length:varint replay
+
+    only a save of this version is resumed. it holds a single game in the middle and the
+    inside of the engine, which changes between versions, so another one is refused and
+    a new game started, unlike a replay which is kept for good.
+*/
+
+// a game left in the middle, with its replay so far so that it is recorded whole.
+pub(crate) struct SavedGame {
+    pub(crate) engine: Engine,
+    pub(crate) recording: Replay,
+    pub(crate) finesse: Finesse,
+    // when the game started, in seconds since the epoch.
+    pub(crate) started: u64,
+}
+
+impl SavedGame {
+    const MAGIC: &'static [u8; 4] = b"TSAV";
+    const VERSION: u8 = 1;
+    pub(crate) const FILE_NAME: &'static str = "save.dat";
+
+    pub(crate) fn encode(
+        engine: &Engine,
+        recording: &Replay,
+        finesse: &Finesse,
+        started: u64,
+    ) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.bytes(Self::MAGIC);
+        w.u8(Self::VERSION);
+        w.u64(started);
+        engine.encode(&mut w);
+        finesse.encode(&mut w);
+        let replay = recording.encode();
+        w.varint(replay.len() as u64);
+        w.bytes(&replay);
+        w.into_bytes()
+    }
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(Self::MAGIC.len())? != Self::MAGIC {
+            return Err("not a saved game".to_string());
+        }
+        match r.u8()? {
+            Self::VERSION => {}
+            v => return Err(format!("a save of version {} cannot be resumed", v)),
+        }
+        let started = r.u64()?;
+        let engine = Engine::decode(&mut r)?;
+        let finesse = Finesse::decode(&mut r)?;
+        let len = r.varint()? as usize;
+        let recording = Replay::decode(r.bytes(len)?)?;
+        if !r.is_empty() {
+            return Err("trailing bytes after the saved game".to_string());
+        }
+        Ok(Self {
+            engine,
+            recording,
+            finesse,
+            started,
+        })
+    }
+    pub(crate) fn save(
+        path: &Path,
+        engine: &Engine,
+        recording: &Replay,
+        finesse: &Finesse,
+        started: u64,
+    ) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, Self::encode(engine, recording, finesse, started))
+    }
+    // None when no game was left.
+    pub(crate) fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(bytes) => Self::decode(&bytes)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    pub(crate) fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mode::Mode, ruleset::Ruleset};
+
+    #[test]
+    fn broken_board() {
+        let engine = Engine::new(3, Ruleset::guideline());
+        let recording = Replay::new(3, Ruleset::guideline(), Mode::Marathon);
+        let bytes = SavedGame::encode(&engine, &recording, &Finesse::default(), 0);
+        assert!(SavedGame::decode(&bytes).is_ok());
+        // the ground, then the first row with its walls.
+        let ground = bytes
+            .windows(4)
+            .position(|w| w == [0xFF, 0xFF, 0x07, 0xE0])
+            .unwrap();
+        let mut broken = bytes;
+        broken[ground] = 0;
+        assert!(SavedGame::decode(&broken).is_err());
+    }
+
+    #[test]
+    fn other_version() {
+        let engine = Engine::new(3, Ruleset::guideline());
+        let recording = Replay::new(3, Ruleset::guideline(), Mode::Marathon);
+        let mut bytes = SavedGame::encode(&engine, &recording, &Finesse::default(), 0);
+        bytes[SavedGame::MAGIC.len()] = SavedGame::VERSION + 1;
+        assert!(SavedGame::decode(&bytes).is_err());
+    }
+}
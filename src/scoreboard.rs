@@ -0,0 +1,139 @@
+// -- scoreboard.rs --
+
+use crate::scores::{Entry, HighScores};
+use fltk::{
+    button::Button,
+    draw,
+    enums::{Align, Color, Font, FrameType},
+    frame::Frame,
+    prelude::{GroupExt, WidgetBase, WidgetExt, WindowExt},
+    widget::Widget,
+    window::DoubleWindow,
+};
+use std::{cell::Cell, rc::Rc};
+
+// --
+
+// a table of high scores in a modal window, the entry just made is highlighted.
+pub(crate) struct ScoreBoard;
+
+impl ScoreBoard {
+    const WIDTH: i32 = 520;
+    const ROW: i32 = 24;
+    const COLUMNS: [(&'static str, i32); 7] = [
+        ("#", 30),
+        ("Name", 130),
+        ("Score", 80),
+        ("Lines", 50),
+        ("Level", 50),
+        ("Time", 60),
+        ("Date", 100),
+    ];
+
+    // true if the player wants to play again, which is only asked after a game. the summary
+    // of that game goes over the table.
+    pub(crate) fn show(
+        title: &str,
+        summary: Option<&str>,
+        entries: &[Entry],
+        highlight: Option<usize>,
+        again: bool,
+    ) -> bool {
+        let table_height = Self::ROW * (HighScores::TOP as i32 + 1);
+        let top = if summary.is_some() { 10 + Self::ROW } else { 0 };
+        let mut wind = DoubleWindow::default()
+            .with_size(Self::WIDTH, top + 20 + table_height + 60)
+            .center_screen()
+            .with_label(title);
+        if let Some(summary) = summary {
+            let mut frame = Frame::new(10, 10, Self::WIDTH - 20, Self::ROW, "");
+            frame.set_label(summary);
+            frame.set_label_font(Font::HelveticaBold);
+            frame.set_label_size(14);
+        }
+        let mut table = Widget::new(10, top + 10, Self::WIDTH - 20, table_height, "");
+        let entries = entries.to_vec();
+        table.draw(move |w| Self::draw(w, &entries, highlight));
+
+        let result = Rc::new(Cell::new(false));
+        let y = top + 20 + table_height + 10;
+        if again {
+            let mut yes = Button::new(Self::WIDTH - 250, y, 110, 30, "&Try again");
+            let mut no = Button::new(Self::WIDTH - 130, y, 110, 30, "&Quit");
+            let (r, mut w) = (result.clone(), wind.clone());
+            yes.set_callback(move |_| {
+                r.set(true);
+                w.hide();
+            });
+            let mut w = wind.clone();
+            no.set_callback(move |_| w.hide());
+        } else {
+            let mut close = Button::new(Self::WIDTH - 130, y, 110, 30, "&Close");
+            let mut w = wind.clone();
+            close.set_callback(move |_| w.hide());
+        }
+        wind.end();
+        wind.make_modal(true);
+        wind.show();
+        while wind.shown() {
+            fltk::app::wait();
+        }
+        result.get()
+    }
+    fn draw(w: &Widget, entries: &[Entry], highlight: Option<usize>) {
+        draw::draw_box(
+            FrameType::FlatBox,
+            w.x(),
+            w.y(),
+            w.width(),
+            w.height(),
+            Color::Black,
+        );
+        let mut y = w.y();
+        Self::draw_row(
+            w.x(),
+            y,
+            Self::COLUMNS.map(|(c, _)| c.to_string()),
+            Color::Yellow,
+        );
+        for (i, e) in entries.iter().enumerate() {
+            y += Self::ROW;
+            let color = if Some(i) == highlight {
+                draw::draw_rect_fill(w.x(), y, w.width(), Self::ROW, Color::DarkRed);
+                Color::Yellow
+            } else {
+                Color::White
+            };
+            let row = [
+                (i + 1).to_string(),
+                e.name.clone(),
+                e.score.to_string(),
+                e.lines.to_string(),
+                e.level.to_string(),
+                e.duration(),
+                e.day(),
+            ];
+            Self::draw_row(w.x(), y, row, color);
+        }
+        if entries.is_empty() {
+            draw::set_draw_color(Color::White);
+            draw::draw_text2(
+                "No games yet",
+                w.x(),
+                w.y(),
+                w.width(),
+                w.height(),
+                Align::Center,
+            );
+        }
+    }
+    fn draw_row(x: i32, y: i32, cells: [String; 7], color: Color) {
+        draw::set_draw_color(color);
+        draw::set_font(Font::Helvetica, 14);
+        let mut x = x + 5;
+        for (cell, (_, width)) in cells.iter().zip(Self::COLUMNS.iter()) {
+            draw::draw_text2(cell, x, y, *width - 5, Self::ROW, Align::Left);
+            x += width;
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// -- scores.rs --
+
+use crate::{gravity::GravityCurve, mode::Mode};
+use std::{fmt::Write as _, fs, io, path::Path};
+
+// --
+
+// e.g. 1:23.417
+pub(crate) fn clock(frames: u32) -> String {
+    let ms = frames as u64 * 1000 / GravityCurve::FRAMES_PER_SECOND as u64;
+    format!("{}:{:02}.{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) name: String,
+    pub(crate) score: i32,
+    pub(crate) lines: i32,
+    pub(crate) level: i32,
+    // how long the game lasted.
+    pub(crate) frames: u32,
+    // when the game ended, in seconds since the epoch.
+    pub(crate) date: u64,
+}
+
+impl Entry {
+    pub(crate) fn duration(&self) -> String {
+        clock(self.frames)
+    }
+    // e.g. 2024-03-01, in utc.
+    pub(crate) fn day(&self) -> String {
+        // days to a civil date, from howard hinnant's algorithms.
+        let z = (self.date / 86400) as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let d = doy - (153 * mp + 2) / 5 + 1;
+        let m = if mp < 10 { mp + 3 } else { mp - 9 };
+        let y = yoe + era * 400 + (m <= 2) as i64;
+        format!("{}-{:02}-{:02}", y, m, d)
+    }
+}
+
+// --
+
+// the best games of one mode played with one ruleset, the best first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Table {
+    mode: Mode,
+    ruleset: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct HighScores {
+    tables: Vec<Table>,
+}
+
+impl HighScores {
+    pub(crate) const FILE_NAME: &'static str = "scores.txt";
+    pub(crate) const TOP: usize = 10;
+
+    pub(crate) fn table(&self, mode: Mode, ruleset: &str) -> &[Entry] {
+        self.tables
+            .iter()
+            .find(|t| t.mode == mode && t.ruleset == ruleset)
+            .map_or(&[], |t| &t.entries)
+    }
+    pub(crate) fn qualifies(&self, mode: Mode, ruleset: &str, entry: &Entry) -> bool {
+        let table = self.table(mode, ruleset);
+        table.len() < Self::TOP || table.iter().any(|e| mode.better(entry, e))
+    }
+    // the rank of the entry, if it made it into the table.
+    pub(crate) fn insert(&mut self, mode: Mode, ruleset: &str, entry: Entry) -> Option<usize> {
+        let i = match self
+            .tables
+            .iter()
+            .position(|t| t.mode == mode && t.ruleset == ruleset)
+        {
+            Some(i) => i,
+            None => {
+                self.tables.push(Table {
+                    mode,
+                    ruleset: ruleset.to_string(),
+                    entries: Vec::new(),
+                });
+                self.tables.len() - 1
+            }
+        };
+        let entries = &mut self.tables[i].entries;
+        // after the games as good, they were there first.
+        let rank = entries
+            .iter()
+            .position(|e| mode.better(&entry, e))
+            .unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(Self::TOP);
+        (rank < Self::TOP).then_some(rank)
+    }
+
+    // a line a game: mode, ruleset, name, score, lines, level, frames, date, split by tabs.
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut scores = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let bad = || format!("line {}: expected 8 fields split by tabs", i + 1);
+            if fields.len() != 8 {
+                return Err(bad());
+            }
+            let entry = Entry {
+                name: fields[2].to_string(),
+                score: fields[3].parse().map_err(|_| bad())?,
+                lines: fields[4].parse().map_err(|_| bad())?,
+                level: fields[5].parse().map_err(|_| bad())?,
+                frames: fields[6].parse().map_err(|_| bad())?,
+                date: fields[7].parse().map_err(|_| bad())?,
+            };
+            let mode = Mode::by_name(fields[0]).ok_or_else(bad)?;
+            scores.insert(mode, fields[1], entry);
+        }
+        Ok(scores)
+    }
+    pub(crate) fn to_text(&self) -> String {
+        let mut text = String::from("# mode\truleset\tname\tscore\tlines\tlevel\tframes\tdate\n");
+        for t in self.tables.iter() {
+            for e in t.entries.iter() {
+                let _ = writeln!(
+                    text,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    t.mode.name(),
+                    t.ruleset,
+                    e.name,
+                    e.score,
+                    e.lines,
+                    e.level,
+                    e.frames,
+                    e.date
+                );
+            }
+        }
+        text
+    }
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: i32) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+            lines: 12,
+            level: 2,
+            frames: 3 * 60 * 60 + 7 * 60,
+            date: 1_709_251_200,
+        }
+    }
+
+    #[test]
+    fn top_ten() {
+        let mut s = HighScores::default();
+        for i in 0..10 {
+            assert_eq!(
+                s.insert(Mode::Marathon, "guideline", entry("a", i * 100)),
+                Some(0)
+            );
+        }
+        assert!(!s.qualifies(Mode::Marathon, "guideline", &entry("b", 0)));
+        assert_eq!(
+            s.insert(Mode::Marathon, "guideline", entry("b", 450)),
+            Some(5)
+        );
+        assert_eq!(s.table(Mode::Marathon, "guideline").len(), HighScores::TOP);
+        assert_eq!(s.table(Mode::Marathon, "classic").len(), 0);
+        assert_eq!(HighScores::parse(&s.to_text()), Ok(s));
+    }
+
+    #[test]
+    fn sprint_is_by_time() {
+        let sprint = Mode::Sprint { lines: 40 };
+        let mut s = HighScores::default();
+        let slow = Entry {
+            frames: 9000,
+            ..entry("a", 900)
+        };
+        s.insert(sprint, "guideline", slow);
+        let fast = Entry {
+            frames: 6000,
+            ..entry("b", 100)
+        };
+        assert_eq!(s.insert(sprint, "guideline", fast), Some(0));
+        assert_eq!(HighScores::parse(&s.to_text()), Ok(s));
+    }
+
+    #[test]
+    fn formats() {
+        let e = entry("a", 0);
+        assert_eq!(e.duration(), "3:07.000");
+        assert_eq!(clock(83 * 60 + 25), "1:23.416");
+        assert_eq!(e.day(), "2024-03-01");
+    }
+}
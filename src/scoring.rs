@@ -0,0 +1,170 @@
+// -- scoring.rs --
+
+use crate::codec::{Reader, Writer};
+
+// --
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+// what a locked block did, reported to the ui as e.g. "B2B T-Spin Double".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Clear {
+    pub(crate) lines: i32,
+    pub(crate) spin: Spin,
+    pub(crate) b2b: bool,
+    // how many clears in a row came before this one.
+    pub(crate) combo: i32,
+    pub(crate) perfect: bool,
+}
+
+impl Clear {
+    // tetrises and spins which clear lines keep the back-to-back chain going.
+    pub(crate) fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.spin != Spin::None && self.lines > 0)
+    }
+    pub(crate) fn name(&self) -> String {
+        const LINES: [&str; 5] = ["", "Single", "Double", "Triple", "Tetris"];
+        let lines = LINES[self.lines.clamp(0, 4) as usize];
+        let mut name = match self.spin {
+            Spin::None => lines.to_string(),
+            Spin::Mini => format!("T-Spin Mini {}", lines),
+            Spin::Full => format!("T-Spin {}", lines),
+        };
+        name = name.trim_end().to_string();
+        if self.b2b {
+            name = format!("B2B {}", name);
+        }
+        if self.combo > 0 {
+            name = format!("{}\n{} Combo", name, self.combo);
+        }
+        if self.perfect {
+            name = format!("{}\nPerfect Clear", name);
+        }
+        name
+    }
+}
+
+// the guideline scoring, every lock is scored with what came before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Scoring {
+    b2b: bool,
+    combo: i32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            b2b: false,
+            combo: -1,
+        }
+    }
+}
+
+impl Scoring {
+    const SOFT_DROP: i32 = 1;
+    const HARD_DROP: i32 = 2;
+    const COMBO: i32 = 50;
+
+    pub(crate) fn soft_drop(cells: i32) -> i32 {
+        cells * Self::SOFT_DROP
+    }
+    pub(crate) fn hard_drop(cells: i32) -> i32 {
+        cells * Self::HARD_DROP
+    }
+    // the clear (if it is worth telling) and the points for it.
+    pub(crate) fn lock(
+        &mut self,
+        lines: i32,
+        spin: Spin,
+        perfect: bool,
+        level: i32,
+    ) -> (Option<Clear>, i32) {
+        let mut clear = Clear {
+            lines,
+            spin,
+            b2b: false,
+            combo: 0,
+            perfect,
+        };
+        let mut points = match (spin, lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, _) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        };
+
+        if lines > 0 {
+            let difficult = clear.is_difficult();
+            if difficult && self.b2b {
+                clear.b2b = true;
+                points = points * 3 / 2;
+            }
+            self.b2b = difficult;
+            self.combo += 1;
+            clear.combo = self.combo;
+        } else {
+            self.combo = -1;
+        }
+        if perfect {
+            points += match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if clear.b2b => 3200,
+                _ => 2000,
+            };
+        }
+        points = points * level + Self::COMBO * clear.combo * level;
+
+        let telling = lines > 0 || spin != Spin::None;
+        (telling.then_some(clear), points)
+    }
+    pub(crate) fn encode(&self, w: &mut Writer) {
+        w.bool(self.b2b);
+        w.i32(self.combo);
+    }
+    pub(crate) fn decode(r: &mut Reader) -> Result<Self, String> {
+        Ok(Self {
+            b2b: r.bool()?,
+            combo: r.i32()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_to_back_and_combo() {
+        let mut s = Scoring::default();
+        let (c, p) = s.lock(4, Spin::None, false, 1);
+        assert_eq!(c.map(|c| c.name()), Some("Tetris".to_string()));
+        assert_eq!(p, 800);
+
+        let (c, p) = s.lock(2, Spin::Full, false, 2);
+        let c = c.unwrap();
+        assert!(c.b2b);
+        assert_eq!(c.name(), "B2B T-Spin Double\n1 Combo");
+        assert_eq!(p, 1800 * 2 + 50 * 2);
+
+        let (c, _) = s.lock(0, Spin::None, false, 2);
+        assert_eq!(c, None);
+        let (c, p) = s.lock(1, Spin::None, false, 1);
+        assert_eq!(c.map(|c| (c.b2b, c.combo)), Some((false, 0)));
+        assert_eq!(p, 100);
+    }
+}
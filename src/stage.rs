@@ -1,270 +1,151 @@
 // -- stage.rs --
 
-use crate::{blocks::Block, boards::UnsafeFrom, tetris::TetrisWindow};
+use crate::{blocks::Block, boards::BlockBoard, engine::Engine};
 use fltk::{
     draw,
-    enums::{Event, FrameType, Color},
+    enums::{Align, Color, FrameType},
     prelude::{WidgetBase, WidgetExt},
     widget::*,
 };
-use std::ops::{Deref, DerefMut};
+use std::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
 
 // --
 
-/*
-
-       first column is 0
-           |           last colume is 15
-           |                 |
-           V                 v
-    20  0b_1110_0000_0000_0111      <-- row number: 20
-    19  0b_1110_0000_0000_0111
-    18  0b_1110_0000_0000_0111
-    17  0b_1110_0000_0000_0111
-    16  0b_1110_0000_0000_0111
-    15  0b_1110_0000_0000_0111
-    14  0b_1110_0000_0000_0111
-    13  0b_1110_0000_0000_0111
-    12  0b_1110_0000_0000_0111
-    11  0b_1110_0000_0000_0111          dropping block
-    10  0b_1110_0010_0000_0111      <-- row: 10
-    9   0b_1110_0011_0000_0111          col: 6
-    8   0b_1110_0001_0000_0111
-    7   0b_1110_0000_0000_0111
-    6   0b_1110_0000_0000_0111
-    5   0b_1110_0000_0000_0111
-    4   0b_1110_0000_0000_0111
-    3   0b_1110_0000_0000_0111
-    2   0b_1110_0000_0000_0111
-    1   0b_1110_0000_0000_0111
-    0   0b_1111_1111_1111_1111      < --- row number: 0
-
-*/
-
-pub(crate) enum Status {
-    NeedBlock,
-    Dropping,
-    Freeze(i32),
+// what is drawn over the board.
+#[derive(Default)]
+struct Overlay {
+    message: Option<String>,
+    paused: bool,
+    // the lines of garbage on their way.
+    incoming: i32,
 }
 
+// the board of an engine, the window owning the engine handles the keys.
 pub(crate) struct Stage {
     wid: Widget,
-    rows: [u16; Stage::ROWS_COUNT as usize],
-    dropping: Option<Block>,
-    col: i32,
-    row: i32,
-    shadow_row: i32,
+    overlay: Rc<RefCell<Overlay>>,
 }
 
 impl Stage {
-    const COLS_COUNT: i32 = 16;
-    const ROWS_COUNT: i32 = 21;
-    const DEFAULT_ROW_DATA: u16 = 0b1110_0000_0000_0111;
-    const GROUND_ROW_DATA: u16 = 0b1111_1111_1111_1111;
-    const LEFT_EDGE_COL: i32 = 3;
+    const METER_WIDTH: i32 = 6;
 
-    pub(crate) fn new(x: i32, y: i32, w: i32, h: i32, label: &'static str) -> Box<Self> {
-        let mut rows = [Self::DEFAULT_ROW_DATA; Self::ROWS_COUNT as usize];
-        rows[0] = Self::GROUND_ROW_DATA;
-        let wid = Widget::new(x, y, w, h, label);
-        let mut ptr = Box::new(Self {
-            wid,
-            rows,
-            dropping: None,
-            col: 7,
-            row: Self::ROWS_COUNT - 1,
-            shadow_row: 0,
-        });
+    pub(crate) fn new(x: i32, y: i32, w: i32, h: i32, engine: Rc<RefCell<Engine>>) -> Self {
+        let mut wid = Widget::new(x, y, w, h, "");
+        let overlay = Rc::new(RefCell::new(Overlay::default()));
 
-        let bb = ptr.as_mut() as *mut Self;
-        ptr.wid.handle(move |_, ev| {
-            let bb = Self::unsafe_mut_from(bb);
-            bb.handle(ev)
-        });
-        let bb = ptr.as_mut() as *mut Self;
-        ptr.wid.draw(move |_| {
-            let bb = Self::unsafe_mut_from(bb);
-            bb.draw();
+        let o = overlay.clone();
+        wid.draw(move |w| {
+            // the engine is only borrowed for a change in between two redraws.
+            if let Ok(engine) = engine.try_borrow() {
+                Self::draw(w, &engine, &o.borrow());
+            }
         });
 
-        ptr.wid.redraw();
-        ptr
+        wid.redraw();
+        Self { wid, overlay }
     }
-    fn draw(&self) {
+    fn draw(w: &Widget, engine: &Engine, overlay: &Overlay) {
         draw::draw_box(
             FrameType::FlatBox,
-            self.wid.x(),
-            self.wid.y(),
-            self.wid.width(),
-            self.wid.height(),
+            w.x(),
+            w.y(),
+            w.width(),
+            w.height(),
             Color::Black,
         );
-        self.draw_background();
-        if let Some(b) = self.dropping.as_ref() {
-            let x = (self.col - Self::LEFT_EDGE_COL) * Block::cell_size();
-            let y = (Self::ROWS_COUNT - 1 - self.row) * Block::cell_size();
-            let y2 = (Self::ROWS_COUNT - 1 - self.shadow_row) * Block::cell_size();
-
-            b.draw(x, y2, Some(Color::from_rgb(30, 30, 30)));
-            b.draw(x, y, None);
+        // nothing to plan with while the game is paused.
+        if overlay.paused {
+            draw::set_draw_color(Color::White);
+            draw::set_font(draw::font(), 32);
+            draw::draw_text2("Paused", w.x(), w.y(), w.width(), w.height(), Align::Center);
+            return;
+        }
+        Self::draw_background(w, engine);
+
+        // the hidden rows are over the board.
+        draw::push_clip(w.x(), w.y(), w.width(), w.height());
+        let cell = Self::cell_size(w);
+        if let Some(b) = engine.dropping() {
+            let x = w.x() + (engine.col() - Engine::LEFT_EDGE_COL) * cell;
+            let y = w.y() + (Engine::VISIBLE_ROWS - engine.row()) * cell;
+            let y2 = w.y() + (Engine::VISIBLE_ROWS - engine.shadow_row()) * cell;
+
+            BlockBoard::draw_block(b, x, y2, cell, Some(Color::from_rgb(30, 30, 30)));
+            BlockBoard::draw_block(b, x, y, cell, None);
         };
-    }
-    pub(crate) fn is_fulled(&mut self, b: Block) -> bool {
-        self.dropping = Some(b);
-        self.row = Self::ROWS_COUNT - 1;
-        self.col = 7;
-        self.shadow_row = 0;
-        self.shadow();
-        self.wid.redraw();
+        draw::pop_clip();
+
+        if overlay.incoming > 0 {
+            let h = overlay.incoming.min(Engine::VISIBLE_ROWS) * cell;
+            draw::draw_rect_fill(
+                w.x(),
+                w.y() + w.height() - h,
+                Self::METER_WIDTH,
+                h,
+                Color::Red,
+            );
+        }
 
-        self.collide_at(self.col, self.row, b.data())
-    }
-    pub(crate) fn tick(&mut self) -> Status {
-        let mut status = Status::NeedBlock;
-        if let Some(block) = self.dropping.as_ref() {
-            if self.collide_at(self.col, self.row - 1, block.data()) {
-                status = Status::Freeze(self.freeze());
-            } else {
-                self.row -= 1;
-                status = Status::Dropping;
-            }
-            self.wid.redraw();
+        if let Some(message) = overlay.message.as_ref() {
+            draw::set_draw_color(Color::White);
+            draw::set_font(draw::font(), 24.min(cell));
+            draw::draw_text2(
+                message,
+                w.x(),
+                w.y() + w.height() / 4,
+                w.width(),
+                cell * Block::CELLS_COUNT,
+                Align::Center,
+            );
         }
-        status
     }
-    pub(crate) fn reset(&mut self) {
-        self.dropping.take();
-        self.rows
-            .iter_mut()
-            .skip(1)
-            .for_each(|r| *r = Self::DEFAULT_ROW_DATA);
+    // a few words over the board, e.g. what the last block cleared.
+    pub(crate) fn set_message(&mut self, message: Option<String>) {
+        self.overlay.borrow_mut().message = message;
         self.wid.redraw();
     }
-    fn shadow(&mut self) {
-        if let Some(block) = self.dropping {
-            self.shadow_row = self.row;
-            while !self.collide_at(self.col, self.shadow_row - 1, block.data()) {
-                self.shadow_row -= 1;
-            }
-        }
-    }
-    fn freeze(&mut self) -> i32 {
-        let mut removed = 0;
-        if let Some(b) = self.dropping.as_ref() {
-            let mut d = b.data();
-            for i in 0..Block::CELLS_COUNT {
-                self.rows[std::cmp::max(self.row - i, 0) as usize] |= (d & 0xF000) >> self.col;
-                d <<= Block::CELLS_COUNT;
-            }
-
-            // remove the lines which are full
-            let v = self.rows.to_vec();
-            let mut it = v
-                .iter()
-                .skip(1)
-                .filter(|r| {
-                    if **r == Self::GROUND_ROW_DATA {
-                        removed += 1;
-                    }
-                    **r != Self::GROUND_ROW_DATA
-                })
-                .map(|r| *r);
-            for r in self.rows.iter_mut().skip(1) {
-                if *r == Self::DEFAULT_ROW_DATA {
-                    break;
-                }
-                *r = it.next().or(Some(Self::DEFAULT_ROW_DATA)).unwrap();
-            }
-        }
-        self.dropping.take();
-        removed
-    }
-    fn collide_at(&self, col: i32, row: i32, data: u16) -> bool {
-        let mut pos_data = 0_u16;
-        for i in 0..Block::CELLS_COUNT {
-            pos_data <<= Block::CELLS_COUNT;
-            pos_data |= (self.rows[std::cmp::max(row - i, 0) as usize]
-                >> (Self::COLS_COUNT - col - Block::CELLS_COUNT))
-                & 0x000F;
-        }
-        pos_data & data > 0
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.overlay.borrow_mut().paused = paused;
+        self.wid.redraw();
     }
-    fn on_keydown(&mut self, s: String) -> bool {
-        let mut ret = true;
-        if let Some(block) = self.dropping.as_ref() {
-            match s.as_str() {
-                "a" => {
-                    if !self.collide_at(self.col - 1, self.row, block.data()) {
-                        self.col -= 1;
-                    }
-                }
-                "d" => {
-                    if !self.collide_at(self.col + 1, self.row, block.data()) {
-                        self.col += 1;
-                    }
-                }
-                "q" => {
-                    let mut b2 = *block;
-                    b2.posture.anticlockwise();
-                    if !self.collide_at(self.col, self.row, b2.data()) {
-                        self.dropping
-                            .as_mut()
-                            .and_then(|b| Some(b.posture.anticlockwise()));
-                    }
-                }
-                "e" => {
-                    let mut b2 = *block;
-                    b2.posture.clockwise();
-                    if !self.collide_at(self.col, self.row, b2.data()) {
-                        self.dropping
-                            .as_mut()
-                            .and_then(|b| Some(b.posture.clockwise()));
-                    }
-                }
-                "s" => {
-                    if !self.collide_at(self.col, self.row - 1, block.data()) {
-                        self.row -= 1;
-                    }
-                }
-                " " => {
-                    self.row = self.shadow_row; // fall down
-                    TetrisWindow::get_mut().recount(); // reset the timeout count
-                }
-                "\u{1b}" => ret = false, // ESC received
-                _ => {}
-            }
-        }
-
-        if ret {
-            self.shadow();
+    // a red bar up the left edge, a cell high for each line.
+    pub(crate) fn set_incoming(&mut self, lines: i32) {
+        if self.overlay.borrow().incoming != lines {
+            self.overlay.borrow_mut().incoming = lines;
             self.wid.redraw();
         }
-        ret
     }
-    fn handle(&mut self, ev: Event) -> bool {
-        match ev {
-            Event::Focus | Event::Unfocus => true,
-            Event::KeyDown => self.on_keydown(fltk::app::event_text()),
-            _ => false,
-        }
+    // the cells fit the width of the board, a small one shows e.g. the other side.
+    fn cell_size(w: &Widget) -> i32 {
+        w.width() / Engine::FIELD_COLS
     }
-    fn draw_background(&self) {
+    fn draw_background(w: &Widget, engine: &Engine) {
+        let cell = Self::cell_size(w);
+        let inner = cell - BlockBoard::CELL_EDGE * 2;
         // we draw these cells from bottom to top.
-        let mut cell_x = self.wid.x() + Block::CELL_EDGE;
-        let mut cell_y =
-            self.wid.y() + Block::CELL_EDGE + Block::cell_size() * (Self::ROWS_COUNT - 2);
+        let mut cell_x = w.x() + BlockBoard::CELL_EDGE;
+        let mut cell_y = w.y() + BlockBoard::CELL_EDGE + cell * (Engine::VISIBLE_ROWS - 1);
 
         draw::set_draw_color(Color::Dark3);
-        for row in self.rows.iter().skip(1) {
+        for row in engine
+            .rows()
+            .iter()
+            .skip(1)
+            .take(Engine::VISIBLE_ROWS as usize)
+        {
             for i in 3..13 {
                 let mask = 0b1000_0000_0000_0000 >> i;
                 if row & mask > 0 {
-                    draw::draw_rectf(cell_x, cell_y, Block::CELL_INNER, Block::CELL_INNER);
+                    draw::draw_rectf(cell_x, cell_y, inner, inner);
                 }
-                cell_x += Block::cell_size();
+                cell_x += cell;
             }
-            cell_x = self.wid.x() + Block::CELL_EDGE;
-            cell_y -= Block::cell_size();
+            cell_x = w.x() + BlockBoard::CELL_EDGE;
+            cell_y -= cell;
         }
     }
 }
@@ -280,4 +161,3 @@ impl DerefMut for Stage {
         &mut self.wid
     }
 }
-impl UnsafeFrom for Stage {}
@@ -1,155 +1,697 @@
 // -- tetris.rs
 
-use crate::blocks::Block;
 use crate::boards::{BlockBoard, TextBoard, ValueBoard};
-use crate::stage::{Stage, Status};
+use crate::config;
+use crate::engine::{Engine, Status, TopOut};
+use crate::finesse::Finesse;
+use crate::gravity::GravityCurve;
+use crate::input::{Action, AutoRepeat, Bindings};
+use crate::mode::Mode;
+use crate::options::Options;
+use crate::replay::{Player, Replay};
+use crate::rng::GameRng;
+use crate::save::SavedGame;
+use crate::scoreboard::ScoreBoard;
+use crate::scores::{self, Entry, HighScores};
+use crate::stage::Stage;
+use crate::window::{with, FrameTimer};
 use fltk::{
+    enums::{Event, Key, Shortcut},
     group::Pack,
-    prelude::{GroupExt, WidgetBase, WidgetExt, WindowExt},
+    menu::{MenuBar, MenuFlag},
+    prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt, WindowExt},
     window::DoubleWindow,
 };
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::{Rc, Weak},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// --
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
 
 // --
 
-fn tick() {
-    let t = TetrisWindow::get_mut();
-    t.tick();
-    fltk::app::repeat_timeout(t.interval, tick);
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Over {
+    Boomed(TopOut),
+    // the goal of the mode is reached.
+    Finished,
 }
 
 // --
 
-static mut TETRIS_PTR: *mut TetrisWindow = std::ptr::null_mut();
+// every game is shared by its window, its widgets and its timer, which all only hold a weak
+// reference to it, so that several games can be played side by side.
 pub(crate) struct TetrisWindow {
-    pub(crate) stage: Box<Stage>,
-    pub(crate) generator: Box<BlockBoard>,
-    pub(crate) lines: Box<ValueBoard>,
-    pub(crate) level: Box<ValueBoard>,
-    pub(crate) score: Box<ValueBoard>,
-
-    pub(crate) interval: f64,
-    pub(crate) count: usize,
+    wind: DoubleWindow,
+    engine: Rc<RefCell<Engine>>,
+    stage: Stage,
+    generator: BlockBoard,
+    holder: BlockBoard,
+    lines: ValueBoard,
+    level: ValueBoard,
+    score: ValueBoard,
+    time: ValueBoard,
+    bindings: Bindings,
+    repeat: AutoRepeat,
+    // the actions of the keys, applied at the start of the next frame.
+    inputs: Vec<Action>,
+    recording: Replay,
+    mode: Mode,
+    finesse: Finesse,
+    started: u64,
+    player: Option<Player>,
+    save: PathBuf,
+    over: bool,
+
+    previews: usize,
+    frames: u32,
+    message_frames: u32,
+    timer: FrameTimer,
+    me: Weak<RefCell<Self>>,
 }
 
 impl TetrisWindow {
-    const DEFAULT_COUNT: usize = 10;
-    const DEFAULT_INTERVAL: f64 = 0.1;
-    fn new() -> Self {
+    const MESSAGE_FRAMES: u32 = 2 * GravityCurve::FRAMES_PER_SECOND;
+    const MENU_HEIGHT: i32 = 25;
+    const SEEK_FRAMES: u32 = 5 * GravityCurve::FRAMES_PER_SECOND;
+    const REPLAY_HELP: &'static str =
+        "Space: play/pause\nLeft, Right: seek\nUp, Down: speed\nHome: restart";
+    fn build(options: &Options, me: Weak<RefCell<Self>>) -> Self {
+        let player = options
+            .replay
+            .as_ref()
+            .and_then(|path| match Replay::load(path) {
+                Ok(replay) => Some(Player::new(replay)),
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    None
+                }
+            });
+        let saved = match player {
+            Some(_) => None,
+            None if !options.resume => {
+                if options.save.exists() {
+                    eprintln!(
+                        "{}: not resumed, a new game was asked for",
+                        options.save.display()
+                    );
+                }
+                None
+            }
+            // the board of a save is checked like any other, a broken one is left for a
+            // new game.
+            None => SavedGame::load(&options.save).unwrap_or_else(|e| {
+                eprintln!("{}: {}, starting a new game", options.save.display(), e);
+                None
+            }),
+        };
+        let (engine, recording, finesse, started) = match (player.as_ref(), saved) {
+            (Some(p), _) => {
+                let (seed, ruleset) = (p.replay().seed(), p.replay().ruleset().clone());
+                let mut engine = Engine::new(seed, ruleset.clone());
+                p.replay().mode().setup(&mut engine);
+                (
+                    engine,
+                    Replay::new(seed, ruleset, p.replay().mode()),
+                    Finesse::default(),
+                    0,
+                )
+            }
+            (None, Some(saved)) => (saved.engine, saved.recording, saved.finesse, saved.started),
+            (None, None) => {
+                let seed = options.seed.unwrap_or_else(GameRng::random_seed);
+                let ruleset = options.ruleset.clone();
+                let mut engine = Engine::new(seed, ruleset.clone());
+                options.mode.setup(&mut engine);
+                let recording = Replay::new(seed, ruleset, options.mode);
+                (engine, recording, Finesse::default(), unix_time())
+            }
+        };
+        // a saved game waits for the player to come back to it.
+        let resumed = recording.frames() > 0;
         let mut wind = DoubleWindow::default()
-            .with_label("Tetris")
-            .with_size(460, 600)
+            .with_size(620, 600 + Self::MENU_HEIGHT)
             .center_screen();
-        let stage = Stage::new(0, 0, 300, 600, "");
-        let mut pack = Pack::new(311, 40, 150, 600, "");
-        let generator = BlockBoard::new_box(1, 1, 1, Block::size(), "Next:");
-        let lines = ValueBoard::new_box(1, 1, 1, 50, "Lines:");
-        let mut level = ValueBoard::new_box(1, 1, 1, 50, "Level:");
-        let score = ValueBoard::new_box(1, 1, 1, 50, "Score:");
-        TextBoard::new(
+        let engine = Rc::new(RefCell::new(engine));
+        let mut menu = MenuBar::new(0, 0, 620, Self::MENU_HEIGHT, "");
+        let mut pack = Pack::new(10, 40 + Self::MENU_HEIGHT, 140, 600, "");
+        let holder = BlockBoard::new(1, 1, 1, BlockBoard::block_size(), "Hold:");
+        let lines = ValueBoard::new(1, 1, 1, 50, "Lines:");
+        let level = ValueBoard::new(1, 1, 1, 50, "Level:");
+        let score = ValueBoard::new(1, 1, 1, 50, "Score:");
+        let bindings = Bindings::load(&options.keys).unwrap_or_else(|e| {
+            eprintln!("{}: {}", options.keys.display(), e);
+            Bindings::default()
+        });
+        let help = match player {
+            Some(_) => Self::REPLAY_HELP.to_string(),
+            None => bindings.help(),
+        };
+        TextBoard::new(1, 1, 1, 160, help);
+        pack.end();
+        pack.set_spacing(30);
+        let mut stage = Stage::new(160, Self::MENU_HEIGHT, 300, 600, engine.clone());
+        let mut pack = Pack::new(471, 40 + Self::MENU_HEIGHT, 150, 600, "");
+        let generator = BlockBoard::new(
             1,
             1,
             1,
-            150,
-            String::from(
-                r#"q: anticlockwise
-e: clockwise
-a: left
-d: right
-s: down
-space: drop"#,
-            ),
+            BlockBoard::strip_height(options.previews as i32),
+            "Next:",
         );
+        let time = match recording.mode().time_limit() {
+            Some(_) => ValueBoard::new(1, 1, 1, 50, "Time left:"),
+            None => ValueBoard::new(1, 1, 1, 50, "Time:"),
+        };
         pack.end();
+        pack.set_spacing(30);
         wind.end();
-        wind.show();
 
-        pack.set_spacing(40);
-        level.set_value(1);
+        Self::fill_menu(&mut menu, &me, player.is_none());
+        let m = me.clone();
+        stage.handle(move |_, ev| with(&m, |t| t.handle(ev)).unwrap_or(false));
+        let m = me.clone();
+        wind.handle(move |_, ev| {
+            // minimized.
+            if ev == Event::Hide {
+                with(&m, |t| t.pause());
+            }
+            false
+        });
+        wind.show();
 
         Self {
+            wind,
+            engine,
             stage,
             generator,
+            holder,
             lines,
             level,
             score,
-            interval: Self::DEFAULT_INTERVAL,
-            count: 0,
+            time,
+            bindings,
+            repeat: AutoRepeat::new(options.handling),
+            inputs: Vec::new(),
+            frames: recording.frames(),
+            mode: recording.mode(),
+            recording,
+            finesse,
+            started,
+            player,
+            save: options.save.clone(),
+            over: false,
+            previews: options.previews,
+            message_frames: 0,
+            timer: FrameTimer::new(resumed),
+            me,
         }
     }
-    pub(crate) fn new_box() -> Box<Self> {
-        unsafe {
-            assert!(TETRIS_PTR.is_null());
-        }
-
-        let mut tetris = Box::new(Self::new());
-        fltk::app::add_timeout(tetris.interval, tick);
-
-        unsafe {
-            TETRIS_PTR = tetris.as_mut();
+    pub(crate) fn new(options: &Options) -> Rc<RefCell<Self>> {
+        let tetris = Rc::new_cyclic(|me| RefCell::new(Self::build(options, me.clone())));
+        {
+            let mut t = tetris.borrow_mut();
+            t.relabel();
+            t.refresh();
+            if t.timer.paused() {
+                t.show_paused(true);
+            } else {
+                t.start();
+            }
         }
         tetris
     }
-    pub(crate) fn get_mut() -> &'static mut Self {
-        unsafe {
-            assert!(!TETRIS_PTR.is_null());
-            let ptr = TETRIS_PTR;
-            &mut *ptr as &mut Self
+    fn fill_menu(menu: &mut MenuBar, me: &Weak<RefCell<Self>>, playing: bool) {
+        if playing {
+            let m = me.clone();
+            menu.add(
+                "&Game/&New game",
+                Shortcut::None,
+                MenuFlag::Normal,
+                move |_| {
+                    with(&m, |t| {
+                        t.save_replay();
+                        t.clean();
+                    });
+                },
+            );
+            let m = me.clone();
+            menu.add("&Game/&Save", Shortcut::None, MenuFlag::Normal, move |_| {
+                with(&m, |t| t.on_action(Action::Save));
+            });
         }
+        let m = me.clone();
+        menu.add(
+            "&Game/&High scores",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                let table = with(&m, |t| {
+                    t.pause();
+                    let (mode, ruleset) = t.table();
+                    (
+                        format!("High scores - {} - {}", mode.name(), ruleset),
+                        mode,
+                        ruleset,
+                    )
+                });
+                if let Some((title, mode, ruleset)) = table {
+                    let scores = Self::high_scores();
+                    ScoreBoard::show(&title, None, scores.table(mode, &ruleset), None, false);
+                }
+            },
+        );
+        let m = me.clone();
+        menu.add("&Game/&Quit", Shortcut::None, MenuFlag::Normal, move |_| {
+            with(&m, |t| t.wind.hide());
+        });
     }
-    pub(crate) fn recount(&mut self) {
-        self.count = 0;
+    // the timer calls tick() every frame, and asks for another game when this one is over.
+    fn start(&mut self) {
+        let me = self.me.clone();
+        self.timer
+            .start(move || match with(&me, |t| (t.tick(), t.timer.paused())) {
+                Some((Some(over), _)) => {
+                    Self::game_over(&me, over);
+                    false
+                }
+                Some((None, paused)) => !paused,
+                None => false,
+            });
+    }
+    // the seed is shown so that the same game can be played again with --seed.
+    fn relabel(&mut self) {
+        let label = {
+            let engine = self.engine.borrow();
+            let label = format!(
+                "Tetris - {} - {} - seed: {}",
+                self.mode.name(),
+                engine.ruleset().name,
+                engine.seed()
+            );
+            match self.player.as_ref() {
+                Some(p) => format!(
+                    "{} - replay {}x - {}s / {}s",
+                    label,
+                    p.speed(),
+                    self.frames / GravityCurve::FRAMES_PER_SECOND,
+                    p.replay().frames() / GravityCurve::FRAMES_PER_SECOND
+                ),
+                None => label,
+            }
+        };
+        self.wind.set_label(&label);
     }
     fn clean(&mut self) {
-        self.lines.set_value(0);
-        self.level.set_value(1);
-        self.score.set_value(0);
-        self.interval = Self::DEFAULT_INTERVAL;
-
-        self.stage.reset();
+        let seed = GameRng::random_seed();
+        self.engine.borrow_mut().reset(seed);
+        self.mode.setup(&mut self.engine.borrow_mut());
+        self.recording = Replay::new(seed, self.engine.borrow().ruleset().clone(), self.mode);
+        self.finesse = Finesse::default();
+        self.started = unix_time();
+        self.frames = 0;
+        self.over = false;
+        self.inputs.clear();
+        self.relabel();
+        self.refresh();
+        self.timer.resume();
+        self.show_paused(false);
+        self.start();
     }
-    fn tick(&mut self) {
-        if self.count < Self::DEFAULT_COUNT {
-            self.count += 1;
+    fn pause(&mut self) {
+        if let Some(p) = self.player.as_mut() {
+            p.playing = false;
+            self.relabel();
             return;
         }
+        if !self.timer.pause() {
+            return;
+        }
+        self.repeat.release_all();
+        self.show_paused(true);
+    }
+    fn resume(&mut self) {
+        if !self.timer.resume() {
+            return;
+        }
+        self.show_paused(false);
+        self.start();
+    }
+    // the board and the blocks to come are hidden while paused.
+    fn show_paused(&mut self, paused: bool) {
+        self.stage.set_paused(paused);
+        self.generator.set_hidden(paused);
+        self.holder.set_hidden(paused);
+    }
+    fn toggle_pause(&mut self) {
+        if self.timer.paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+    fn refresh(&mut self) {
+        let engine = self.engine.borrow();
+        self.lines.set_value(engine.lines());
+        self.level.set_value(engine.level());
+        self.score.set_value(engine.score());
+        let time = match self.mode.time_limit() {
+            Some(limit) => limit.saturating_sub(self.frames),
+            None => self.frames,
+        };
+        self.time.set_text(scores::clock(time));
+        self.generator
+            .set_blocks(engine.queue().previews(self.previews));
+        self.holder.set_block(engine.hold().copied());
+        self.holder.set_greyed(engine.hold_locked());
+        self.stage.redraw();
+    }
+    // a replay runs at its own speed.
+    fn tick(&mut self) -> Option<Over> {
+        let speed = match self.player.as_ref() {
+            Some(p) if p.playing => p.speed(),
+            Some(_) => 0.0,
+            None => 1.0,
+        };
+        let due = self.timer.due(speed);
 
-        match self.stage.tick() {
-            Status::NeedBlock => {
-                if self.stage.is_fulled(self.generator.next()) {
-                    let s = fltk::app::screen_size();
-                    match fltk::dialog::choice(
-                        (s.0 / 2.0) as i32 - 100,
-                        (s.1 / 2.0) as i32 - 100,
-                        "Boomed!!!\n\nDo you want to try again?",
-                        "&Yes",
-                        "&No",
-                        "",
-                    ) {
-                        0 => self.clean(),
-                        1 => std::process::exit(0),
-                        _ => {}
+        let mut frames = 0;
+        let mut over = None;
+        while over.is_none() && frames < due {
+            frames += 1;
+            if let Some(p) = self.player.as_mut() {
+                if self.frames >= p.replay().frames() {
+                    p.playing = false;
+                    break;
+                }
+            }
+            over = self.frame();
+        }
+        if frames > 0 {
+            self.refresh();
+        }
+        match self.player.as_mut() {
+            Some(p) => {
+                if over.is_some() {
+                    p.playing = false;
+                }
+                if frames > 0 {
+                    self.relabel();
+                }
+                None
+            }
+            None => {
+                if over == Some(Over::Finished) {
+                    self.stage
+                        .set_message(Some(self.mode.finish_message().to_string()));
+                }
+                if over.is_some() {
+                    self.over = true;
+                    self.save_replay();
+                    if let Err(e) = SavedGame::remove(&self.save) {
+                        eprintln!("{}: {}", self.save.display(), e);
                     }
-                } else {
-                    self.recount();
                 }
+                over
+            }
+        }
+    }
+    fn frame(&mut self) -> Option<Over> {
+        if self.message_frames > 0 {
+            self.message_frames -= 1;
+            if self.message_frames == 0 {
+                self.stage.set_message(None);
+            }
+        }
+
+        let actions = match self.player.as_mut() {
+            Some(p) => p.actions(self.frames),
+            None => {
+                let mut actions = std::mem::take(&mut self.inputs);
+                let repeated = self.repeat.frame();
+                self.finesse.actions(&actions, &repeated);
+                actions.extend(repeated);
+                for action in actions.iter() {
+                    self.recording.push(self.frames, *action);
+                }
+                actions
             }
-            Status::Freeze(removed) => {
-                if removed > 0 {
-                    self.lines.set_value(self.lines.value() + removed);
+        };
+        self.frames += 1;
+        self.recording.set_frames(self.frames);
 
-                    const LINES_PER_LEVEL: i32 = 10;
-                    let level = self.level.value();
-                    if level * LINES_PER_LEVEL < self.lines.value() {
-                        self.level.set_value(level + 1);
-                        self.interval *= 0.9;
-                    }
+        let status = self.engine.borrow_mut().step(&actions);
+        let top_out = || self.engine.borrow().top_out().unwrap_or(TopOut::Block);
+        match status {
+            None => return Some(Over::Boomed(top_out())),
+            Some(Status::Freeze) => self.freezed(),
+            _ => {}
+        }
+        if !self.mode.rise(&mut self.engine.borrow_mut(), self.frames) {
+            return Some(Over::Boomed(TopOut::Garbage));
+        }
+        self.mode
+            .finished(&self.engine.borrow(), self.frames)
+            .then_some(Over::Finished)
+    }
+    // runs the replay from its start up to the frame, without drawing on the way.
+    fn seek(&mut self, frame: u32) {
+        let player = match self.player.as_mut() {
+            Some(p) => p,
+            None => return,
+        };
+        let mut engine = self.engine.borrow_mut();
+        if frame < self.frames {
+            engine.reset(player.replay().seed());
+            self.mode.setup(&mut engine);
+            player.rewind();
+            self.frames = 0;
+        }
+        let frame = frame.min(player.replay().frames());
+        while self.frames < frame {
+            let actions = player.actions(self.frames);
+            self.frames += 1;
+            if engine.step(&actions).is_none()
+                || !self.mode.rise(&mut engine, self.frames)
+                || self.mode.finished(&engine, self.frames)
+            {
+                break;
+            }
+        }
+        drop(engine);
 
-                    self.score.set_value(self.score.value() + removed * removed);
+        self.message_frames = 0;
+        self.stage.set_message(None);
+        self.refresh();
+    }
+    fn save_game(&self) -> bool {
+        if self.player.is_some() || self.over || self.frames == 0 {
+            return false;
+        }
+        let engine = self.engine.borrow();
+        match SavedGame::save(
+            &self.save,
+            &engine,
+            &self.recording,
+            &self.finesse,
+            self.started,
+        ) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("{}: {}", self.save.display(), e);
+                false
+            }
+        }
+    }
+    fn save_replay(&self) {
+        if self.player.is_some() || self.frames == 0 {
+            return;
+        }
+        let path = Replay::file(self.recording.seed(), self.started);
+        if let Err(e) = self.recording.save(&path) {
+            eprintln!("{}: {}", path.display(), e);
+        }
+    }
+    fn freezed(&mut self) {
+        if self.player.is_none() {
+            if let Some(lock) = self.engine.borrow().last_lock() {
+                self.finesse.locked(lock);
+            }
+        }
+        let clear = self.engine.borrow().last_clear().copied();
+        if let Some(clear) = clear {
+            self.stage.set_message(Some(clear.name()));
+            self.message_frames = Self::MESSAGE_FRAMES;
+        }
+    }
+    // the mode and ruleset the high scores of this game go to.
+    fn table(&self) -> (Mode, String) {
+        let ruleset = self.engine.borrow().ruleset().key();
+        (self.mode, ruleset)
+    }
+    // e.g. 40 lines in 1:23.417 - 2.31 pieces/s - 3 finesse faults, with the points first
+    // when they are the goal.
+    fn summary(&self) -> String {
+        let engine = self.engine.borrow();
+        let seconds = self.frames as f64 / GravityCurve::FRAMES_PER_SECOND as f64;
+        let pps = match self.frames {
+            0 => 0.0,
+            _ => engine.pieces() as f64 / seconds,
+        };
+        let lines = format!("{} lines in {}", engine.lines(), scores::clock(self.frames));
+        let lines = match self.mode.is_race() {
+            true => lines,
+            false => format!("{} points - {}", engine.score(), lines),
+        };
+        format!(
+            "{} - {:.2} pieces/s - {} finesse faults",
+            lines,
+            pps,
+            self.finesse.faults()
+        )
+    }
+    fn high_scores() -> HighScores {
+        let path = config::file(HighScores::FILE_NAME);
+        HighScores::load(&path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            HighScores::default()
+        })
+    }
+    fn game_over(me: &Weak<RefCell<Self>>, over: Over) {
+        let game = with(me, |t| {
+            let engine = t.engine.borrow();
+            let entry = Entry {
+                name: String::new(),
+                score: engine.score(),
+                lines: engine.lines(),
+                level: engine.level(),
+                frames: t.frames,
+                date: unix_time(),
+            };
+            drop(engine);
+            (t.table(), entry, t.summary())
+        });
+        let ((mode, ruleset), entry, summary) = match game {
+            Some(game) => game,
+            None => return,
+        };
+
+        let mut scores = Self::high_scores();
+        let mut rank = None;
+        let counts = over == Over::Finished || mode.counts_boomed();
+        if counts && scores.qualifies(mode, &ruleset, &entry) {
+            if let Some(name) = Self::ask_name() {
+                rank = scores.insert(mode, &ruleset, Entry { name, ..entry });
+                let path = config::file(HighScores::FILE_NAME);
+                if let Err(e) = scores.save(&path) {
+                    eprintln!("{}: {}", path.display(), e);
                 }
             }
-            Status::Dropping => {
-                self.recount();
+        }
+        let title = match over {
+            Over::Boomed(top_out) => format!(
+                "Boomed!!! {} - {} - {}",
+                top_out.name(),
+                mode.name(),
+                ruleset
+            ),
+            Over::Finished => format!("Finished! - {} - {}", mode.name(), ruleset),
+        };
+        let table = scores.table(mode, &ruleset);
+        if ScoreBoard::show(&title, Some(&summary), table, rank, true) {
+            with(me, |t| t.clean());
+        } else {
+            with(me, |t| t.wind.hide());
+        }
+    }
+    fn ask_name() -> Option<String> {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default();
+        let name = fltk::dialog::input_default("A new high score!\n\nYour name:", &user)?;
+        // the table is split by tabs and lines.
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .take(16)
+            .collect();
+        Some(name.trim().to_string()).filter(|n| !n.is_empty())
+    }
+    fn on_action(&mut self, action: Action) -> bool {
+        if action == Action::Pause {
+            self.toggle_pause();
+        } else if action == Action::Save {
+            if self.save_game() {
+                self.stage.set_message(Some("Saved".to_string()));
+                self.message_frames = Self::MESSAGE_FRAMES;
+            }
+        } else if !self.timer.paused() && !self.over {
+            self.inputs.push(action);
+        }
+        true
+    }
+    fn on_replay_key(&mut self, key: Key) -> bool {
+        let player = match self.player.as_mut() {
+            Some(p) => p,
+            None => return false,
+        };
+        if key == Key::from_char(' ') {
+            player.playing = !player.playing;
+        } else if key == Key::Up {
+            player.faster();
+        } else if key == Key::Down {
+            player.slower();
+        } else if key == Key::Left {
+            self.seek(self.frames.saturating_sub(Self::SEEK_FRAMES));
+        } else if key == Key::Right {
+            self.seek(self.frames + Self::SEEK_FRAMES);
+        } else if key == Key::Home {
+            self.seek(0);
+        } else {
+            return false;
+        }
+        self.relabel();
+        true
+    }
+    fn handle(&mut self, ev: Event) -> bool {
+        let key = fltk::app::event_key();
+        match ev {
+            Event::Focus => true,
+            Event::Unfocus => {
+                self.pause();
+                true
             }
+            Event::KeyDown if self.player.is_some() => self.on_replay_key(key),
+            Event::KeyDown => match self.bindings.action(key) {
+                Some(action) if self.repeat.press(key, action) => self.on_action(action),
+                Some(_) => true,
+                None => false,
+            },
+            Event::KeyUp => match self.bindings.action(key) {
+                Some(_) => {
+                    self.repeat.release(key);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
         }
     }
 }
+
+// a game left before it is over is saved to go on with next time, and recorded too.
+impl Drop for TetrisWindow {
+    fn drop(&mut self) {
+        self.save_game();
+        self.save_replay();
+    }
+}
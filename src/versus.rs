@@ -0,0 +1,397 @@
+// -- versus.rs --
+
+use crate::{
+    attack::Incoming,
+    boards::{BlockBoard, TextBoard, ValueBoard},
+    config,
+    engine::{Engine, Status},
+    input::{Action, AutoRepeat, Bindings, Handling},
+    options::Options,
+    rng::GameRng,
+    stage::Stage,
+    window::{with, FrameTimer},
+};
+use fltk::{
+    enums::{Event, Key, Shortcut},
+    group::Pack,
+    menu::{MenuBar, MenuFlag},
+    prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt, WindowExt},
+    window::DoubleWindow,
+};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+// --
+
+// one side of a versus, with a board and keys of its own. online, the other side is
+// on the other computer.
+pub(crate) struct Player {
+    pub(crate) engine: Rc<RefCell<Engine>>,
+    pub(crate) stage: Stage,
+    holder: BlockBoard,
+    generator: BlockBoard,
+    wins: ValueBoard,
+    bindings: Bindings,
+    repeat: AutoRepeat,
+    // the actions of its keys since the last frame.
+    inputs: Vec<Action>,
+    // the garbage sent by the other side, it comes in when a block locks without a clear.
+    pub(crate) incoming: Incoming,
+}
+
+impl Player {
+    const PREVIEWS: usize = 2;
+
+    // the board at x, under the menu, with the boards of the side at side.
+    pub(crate) fn new(
+        x: i32,
+        side: i32,
+        engine: Engine,
+        bindings: Bindings,
+        handling: Handling,
+    ) -> Self {
+        let engine = Rc::new(RefCell::new(engine));
+        let mut pack = Pack::new(side, 40 + VersusWindow::MENU_HEIGHT, 140, 600, "");
+        let holder = BlockBoard::new(1, 1, 1, BlockBoard::block_size(), "Hold:");
+        let generator = BlockBoard::new(
+            1,
+            1,
+            1,
+            BlockBoard::strip_height(Self::PREVIEWS as i32),
+            "Next:",
+        );
+        let wins = ValueBoard::new(1, 1, 1, 50, "Wins:");
+        TextBoard::new(1, 1, 1, 130, bindings.help());
+        pack.end();
+        pack.set_spacing(30);
+        let stage = Stage::new(x, VersusWindow::MENU_HEIGHT, 300, 600, engine.clone());
+
+        Self {
+            engine,
+            stage,
+            holder,
+            generator,
+            wins,
+            bindings,
+            repeat: AutoRepeat::new(handling),
+            inputs: Vec::new(),
+            incoming: Incoming::default(),
+        }
+    }
+    // the keys of a side of this window, from its own file.
+    fn bindings(index: usize) -> Bindings {
+        let path = config::file(Bindings::VERSUS_FILE_NAMES[index]);
+        Bindings::load_or(&path, Bindings::versus(index)).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            Bindings::versus(index)
+        })
+    }
+    pub(crate) fn refresh(&mut self, wins: u32) {
+        let engine = self.engine.borrow();
+        self.generator
+            .set_blocks(engine.queue().previews(Self::PREVIEWS));
+        self.holder.set_block(engine.hold().copied());
+        self.holder.set_greyed(engine.hold_locked());
+        self.wins.set_value(wins as i32);
+        self.stage.set_incoming(self.incoming.lines());
+        self.stage.redraw();
+    }
+    // a new round, on the board the engine was reset to.
+    pub(crate) fn new_round(&mut self) {
+        self.inputs.clear();
+        self.repeat.release_all();
+        self.incoming = Incoming::default();
+        self.stage.set_message(None);
+    }
+    // the board and the blocks to come are hidden while paused.
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.stage.set_paused(paused);
+        self.generator.set_hidden(paused);
+        self.holder.set_hidden(paused);
+    }
+    // the keys held are let go, e.g. when the window loses the focus.
+    pub(crate) fn release_all(&mut self) {
+        self.repeat.release_all();
+    }
+    // None when the key is not one of this side, and no action inside when it is only the
+    // os repeating a held key, the frames do that.
+    pub(crate) fn press(&mut self, key: Key) -> Option<Option<Action>> {
+        let action = self.bindings.action(key)?;
+        Some(self.repeat.press(key, action).then_some(action))
+    }
+    // false when the key is not one of this side.
+    pub(crate) fn release(&mut self, key: Key) -> bool {
+        if self.bindings.action(key).is_none() {
+            return false;
+        }
+        self.repeat.release(key);
+        true
+    }
+    // whether keys were pressed since the last frame.
+    pub(crate) fn has_inputs(&self) -> bool {
+        !self.inputs.is_empty()
+    }
+    // an action of the keys for the next frame.
+    pub(crate) fn push(&mut self, action: Action) {
+        self.inputs.push(action);
+    }
+    // one frame of the board: how it went and the lines it sends, or None when it topped
+    // out.
+    pub(crate) fn frame(&mut self) -> Option<(Status, i32)> {
+        let mut actions = std::mem::take(&mut self.inputs);
+        actions.extend(self.repeat.frame());
+        let mut engine = self.engine.borrow_mut();
+        let status = engine.step(&actions)?;
+        let lines = match status {
+            Status::Freeze => self.incoming.locked(&mut engine)?,
+            _ => 0,
+        };
+        Some((status, lines))
+    }
+}
+
+// --
+
+// two players side by side on one keyboard, with the same blocks. the lines one clears
+// come up as garbage under the other, the first to top out loses the round.
+pub(crate) struct VersusWindow {
+    wind: DoubleWindow,
+    players: [Player; 2],
+    // the match is the best of that many rounds.
+    rounds: u32,
+    wins: [u32; 2],
+    seed: u64,
+
+    timer: FrameTimer,
+    me: Weak<RefCell<Self>>,
+}
+
+impl VersusWindow {
+    pub(crate) const MENU_HEIGHT: i32 = 25;
+    const WIDTH: i32 = 940;
+
+    fn build(options: &Options, rounds: u32, me: Weak<RefCell<Self>>) -> Self {
+        let seed = options.seed.unwrap_or_else(GameRng::random_seed);
+        let mut wind = DoubleWindow::default()
+            .with_size(Self::WIDTH, 600 + Self::MENU_HEIGHT)
+            .center_screen();
+        let mut menu = MenuBar::new(0, 0, Self::WIDTH, Self::MENU_HEIGHT, "");
+        let players = [0, 1].map(|i| {
+            let engine = Engine::new(seed, options.ruleset.clone());
+            let x = 160 + i as i32 * 320;
+            let side = if i == 0 { 10 } else { x + 310 };
+            Player::new(x, side, engine, Player::bindings(i), options.handling)
+        });
+        wind.end();
+
+        let m = me.clone();
+        menu.add(
+            "&Game/&New match",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                with(&m, |t| t.new_match());
+            },
+        );
+        let m = me.clone();
+        menu.add("&Game/&Quit", Shortcut::None, MenuFlag::Normal, move |_| {
+            with(&m, |t| t.wind.hide());
+        });
+        let m = me.clone();
+        wind.handle(move |_, ev| with(&m, |t| t.handle(ev)).unwrap_or(false));
+        wind.show();
+
+        Self {
+            wind,
+            players,
+            rounds,
+            wins: [0; 2],
+            seed,
+            timer: FrameTimer::new(false),
+            me,
+        }
+    }
+    pub(crate) fn new(options: &Options, rounds: u32) -> Rc<RefCell<Self>> {
+        let versus = Rc::new_cyclic(|me| RefCell::new(Self::build(options, rounds, me.clone())));
+        {
+            let mut t = versus.borrow_mut();
+            t.relabel();
+            t.refresh();
+            t.start();
+        }
+        versus
+    }
+    // the timer calls tick() every frame, and tells who won when a round is over.
+    fn start(&mut self) {
+        let me = self.me.clone();
+        self.timer
+            .start(move || match with(&me, |t| (t.tick(), t.timer.paused())) {
+                Some((Some(winner), _)) => {
+                    Self::round_over(&me, winner);
+                    false
+                }
+                Some((None, paused)) => !paused,
+                None => false,
+            });
+    }
+    fn relabel(&mut self) {
+        let label = format!(
+            "Tetris - versus - best of {} - {} : {} - seed: {}",
+            self.rounds, self.wins[0], self.wins[1], self.seed
+        );
+        self.wind.set_label(&label);
+    }
+    fn refresh(&mut self) {
+        for (p, wins) in self.players.iter_mut().zip(self.wins) {
+            p.refresh(wins);
+        }
+    }
+    // both boards start over with the same new blocks.
+    fn new_round(&mut self) {
+        self.seed = GameRng::random_seed();
+        for p in self.players.iter_mut() {
+            p.engine.borrow_mut().reset(self.seed);
+            p.new_round();
+        }
+        self.relabel();
+        self.refresh();
+        self.timer.resume();
+        self.players.iter_mut().for_each(|p| p.set_paused(false));
+        self.start();
+    }
+    fn new_match(&mut self) {
+        self.wins = [0; 2];
+        self.new_round();
+    }
+    fn pause(&mut self) {
+        if !self.timer.pause() {
+            return;
+        }
+        for p in self.players.iter_mut() {
+            p.release_all();
+            p.set_paused(true);
+        }
+    }
+    fn resume(&mut self) {
+        if !self.timer.resume() {
+            return;
+        }
+        self.players.iter_mut().for_each(|p| p.set_paused(false));
+        self.start();
+    }
+    // Some when the round is over, with the winner unless both topped out in the same
+    // frame.
+    fn tick(&mut self) -> Option<Option<usize>> {
+        let due = self.timer.due(1.0);
+        let mut frames = 0;
+        let mut over = None;
+        while over.is_none() && frames < due {
+            frames += 1;
+            over = self.frame();
+        }
+        if frames > 0 {
+            self.refresh();
+        }
+        over
+    }
+    fn frame(&mut self) -> Option<Option<usize>> {
+        let sent = [0, 1].map(|i| self.players[i].frame().map(|(_, lines)| lines));
+        for (i, lines) in sent.iter().enumerate() {
+            self.players[1 - i].incoming.receive(lines.unwrap_or(0));
+        }
+        match sent {
+            [None, None] => Some(None),
+            [None, _] => Some(Some(1)),
+            [_, None] => Some(Some(0)),
+            _ => None,
+        }
+    }
+    fn round_over(me: &Weak<RefCell<Self>>, winner: Option<usize>) {
+        let over = with(me, |t| {
+            for (i, p) in t.players.iter_mut().enumerate() {
+                let message = if Some(i) == winner {
+                    "Winner!"
+                } else {
+                    "Boomed!!!"
+                };
+                p.stage.set_message(Some(message.to_string()));
+            }
+            if let Some(w) = winner {
+                t.wins[w] += 1;
+            }
+            t.relabel();
+            t.refresh();
+            let needed = t.rounds / 2 + 1;
+            let champion = t.wins.iter().position(|w| *w >= needed);
+            (champion, t.wins)
+        });
+        let (champion, wins) = match over {
+            Some(over) => over,
+            None => return,
+        };
+        let round = match winner {
+            Some(w) => format!("Player {} wins the round.", w + 1),
+            None => "Both boomed, nobody wins the round.".to_string(),
+        };
+        match champion {
+            Some(c) => {
+                let text = format!(
+                    "{}\n\nPlayer {} wins the match {} : {}.",
+                    round,
+                    c + 1,
+                    wins[0],
+                    wins[1]
+                );
+                match fltk::dialog::choice2_default(&text, "Quit", "Play again", "") {
+                    Some(1) => with(me, |t| t.new_match()),
+                    _ => with(me, |t| t.wind.hide()),
+                };
+            }
+            None => {
+                fltk::dialog::message_default(&round);
+                with(me, |t| t.new_round());
+            }
+        }
+    }
+    fn handle(&mut self, ev: Event) -> bool {
+        let key = fltk::app::event_key();
+        match ev {
+            Event::Focus => true,
+            // lost the focus, or minimized.
+            Event::Unfocus | Event::Hide => {
+                self.pause();
+                false
+            }
+            Event::KeyDown => {
+                let found = self
+                    .players
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(i, p)| Some((i, p.press(key)?)));
+                let (i, action) = match found {
+                    Some((i, Some(action))) => (i, action),
+                    Some((_, None)) => return true,
+                    None => return false,
+                };
+                match action {
+                    Action::Pause if self.timer.paused() => self.resume(),
+                    Action::Pause => self.pause(),
+                    Action::Save => {}
+                    _ if !self.timer.paused() => self.players[i].push(action),
+                    _ => {}
+                }
+                true
+            }
+            Event::KeyUp => {
+                let mut used = false;
+                for p in self.players.iter_mut() {
+                    used |= p.release(key);
+                }
+                used
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// -- window.rs --
+
+use crate::gravity::GravityCurve;
+use fltk::app::TimeoutHandle;
+use std::{
+    cell::RefCell,
+    rc::Weak,
+    time::{Duration, Instant},
+};
+
+// --
+
+// runs f on a window unless it is gone or busy, e.g. with a dialog of its own. the
+// widgets and timers of a window only hold a weak reference to it.
+pub(crate) fn with<T, R>(me: &Weak<RefCell<T>>, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let t = me.upgrade()?;
+    let mut t = t.try_borrow_mut().ok()?;
+    Some(f(&mut t))
+}
+
+// --
+
+// the frames of a game, run by a timer of fltk. the timer is not exact, so the real time
+// gone by decides how many frames are due. while paused it stops, so neither gravity nor
+// lock delay go on.
+pub(crate) struct FrameTimer {
+    last_tick: Instant,
+    elapsed: Duration,
+    paused: bool,
+    timeout: Option<TimeoutHandle>,
+}
+
+impl FrameTimer {
+    const FRAME: Duration =
+        Duration::from_nanos(1_000_000_000 / GravityCurve::FRAMES_PER_SECOND as u64);
+    // frames to catch up at most, e.g. after a dialog blocked the timer.
+    const MAX_FRAMES_PER_TICK: u32 = 10;
+
+    pub(crate) fn new(paused: bool) -> Self {
+        Self {
+            last_tick: Instant::now(),
+            elapsed: Duration::ZERO,
+            paused,
+            timeout: None,
+        }
+    }
+    // tick is called every frame for as long as it returns true. the dialogs it may run
+    // have their own event loops, which have to find the window unborrowed.
+    pub(crate) fn start(&mut self, mut tick: impl FnMut() -> bool + 'static) {
+        self.stop();
+        self.last_tick = Instant::now();
+        self.elapsed = Duration::ZERO;
+        let handle = fltk::app::add_timeout3(Self::FRAME.as_secs_f64(), move |handle| {
+            if tick() {
+                fltk::app::repeat_timeout3(Self::FRAME.as_secs_f64(), handle);
+            }
+        });
+        self.timeout = Some(handle);
+    }
+    fn stop(&mut self) {
+        if let Some(handle) = self.timeout.take() {
+            fltk::app::remove_timeout3(handle);
+        }
+    }
+    // the frames due since the last tick, at a speed of the game, e.g. of a replay. the
+    // time behind after too many is dropped, e.g. after the computer slept.
+    pub(crate) fn due(&mut self, speed: f64) -> u32 {
+        let now = Instant::now();
+        self.elapsed += (now - self.last_tick).mul_f64(speed);
+        self.last_tick = now;
+        let frames = (self.elapsed.as_nanos() / Self::FRAME.as_nanos()) as u32;
+        if frames > Self::MAX_FRAMES_PER_TICK {
+            self.elapsed = Duration::ZERO;
+            return Self::MAX_FRAMES_PER_TICK;
+        }
+        self.elapsed -= Self::FRAME * frames;
+        frames
+    }
+    pub(crate) fn paused(&self) -> bool {
+        self.paused
+    }
+    // false when it was paused already.
+    pub(crate) fn pause(&mut self) -> bool {
+        if self.paused {
+            return false;
+        }
+        self.paused = true;
+        self.stop();
+        true
+    }
+    // false when it was not paused, the window starts the timer again.
+    pub(crate) fn resume(&mut self) -> bool {
+        if !self.paused {
+            return false;
+        }
+        self.paused = false;
+        true
+    }
+}